@@ -1,8 +1,20 @@
+pub mod bounds;
+pub mod buffers;
+pub mod camera;
+pub mod color;
+pub mod debug_label;
+pub mod descriptors;
 pub mod gpu;
+pub mod memory;
+pub mod pass_list;
 pub mod pipeline;
 pub mod queue_families;
 pub mod render_pass;
 pub mod swapchain;
+pub mod sync;
+pub mod test_pattern;
+pub mod texture_loading;
+pub mod transform;
 pub mod vulkan_object;
 pub mod extra;
 mod shaders;
@@ -0,0 +1,44 @@
+use crate::graphical_core::transform::Vec3;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Aabb { //Axis-aligned bounding box, for frustum culling and picking.
+    pub min: Vec3,
+    pub max: Vec3
+}
+impl Aabb {
+    pub fn from_points(points: impl IntoIterator<Item = Vec3>) -> Option<Self> { //None for an empty set of points.
+        let mut points = points.into_iter();
+        let first = points.next()?;
+        Some(points.fold(Self {min: first, max: first}, |bounds, point| Self {
+            min: Vec3::new(bounds.min.x.min(point.x), bounds.min.y.min(point.y), bounds.min.z.min(point.z)),
+            max: Vec3::new(bounds.max.x.max(point.x), bounds.max.y.max(point.y), bounds.max.z.max(point.z))
+        }))
+    }
+    pub fn center(&self) -> Vec3 {
+        (self.min + self.max) / 2.0
+    }
+    pub fn size(&self) -> Vec3 {
+        self.max - self.min
+    }
+    pub fn contains(&self, point: Vec3) -> bool {
+        (self.min.x..=self.max.x).contains(&point.x) && (self.min.y..=self.max.y).contains(&point.y) && (self.min.z..=self.max.z).contains(&point.z)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graphical_core::vulkan_object::VERTICES;
+
+    #[test]
+    fn from_points_bounds_the_quad() {
+        let bounds = Aabb::from_points(VERTICES.iter().map(|v| v.position.extend(0.0))).unwrap();
+        assert_eq!(bounds.min, Vec3::new(-0.5, -0.5, 0.0));
+        assert_eq!(bounds.max, Vec3::new(0.5, 0.5, 0.0));
+    }
+    #[test]
+    fn from_points_is_none_without_points() {
+        assert_eq!(Aabb::from_points(std::iter::empty()), None);
+    }
+}
@@ -0,0 +1,187 @@
+use std::ffi::c_void;
+use std::mem::{size_of, size_of_val};
+use std::ptr::copy_nonoverlapping;
+use std::sync::Arc;
+use anyhow::{anyhow, Context};
+use vulkanalia::{Device, Instance, vk};
+use vulkanalia::vk::{DeviceV1_0, Handle, HasBuilder, InstanceV1_0};
+use crate::graphical_core::{
+    memory::{find_memory_type, track_allocation, track_free},
+    sync::{Access, PipelineBarrier},
+    vulkan_object::VulkanApplicationData
+};
+
+pub unsafe fn create_buffer(instance: &Instance, device: &Device, data: &VulkanApplicationData, size: vk::DeviceSize, usage: vk::BufferUsageFlags, properties: vk::MemoryPropertyFlags)
+    -> anyhow::Result<(vk::Buffer, vk::DeviceMemory, vk::DeviceSize)> { //Also returns the allocated size, which can be larger than the requested one.
+    let buffer_info = vk::BufferCreateInfo::builder().size(size).usage(usage).sharing_mode(vk::SharingMode::EXCLUSIVE);
+    let buffer = device.create_buffer(&buffer_info, None).context("creating buffer")?;
+
+    let requirements = device.get_buffer_memory_requirements(buffer);
+    let memory_properties = instance.get_physical_device_memory_properties(data.physical_device);
+    let memory_type_index = find_memory_type(&memory_properties, requirements.memory_type_bits, properties).ok_or_else(|| anyhow!("Failed to find a memory type with {:?} for the buffer.", properties))?;
+    let memory_info = vk::MemoryAllocateInfo::builder().allocation_size(requirements.size).memory_type_index(memory_type_index);
+    let memory = device.allocate_memory(&memory_info, None).context("allocating buffer memory")?;
+    track_allocation(requirements.size);
+
+    device.bind_buffer_memory(buffer, memory, 0).context("binding buffer memory")?;
+    Ok((buffer, memory, requirements.size))
+}
+pub unsafe fn destroy_buffer(device: &Device, buffer: vk::Buffer, memory: vk::DeviceMemory, allocated_size: vk::DeviceSize) {
+    device.destroy_buffer(buffer, None);
+    device.free_memory(memory, None);
+    track_free(allocated_size);
+}
+
+pub unsafe fn allocate_buffer<T>(instance: &Instance, device: &Device, data: &VulkanApplicationData, usage: vk::BufferUsageFlags)
+    -> anyhow::Result<(vk::Buffer, vk::DeviceMemory, vk::DeviceSize, *mut c_void)> { //Room for one T, host-visible and persistently mapped so it can be rewritten without remapping. Unmapped by freeing the memory.
+    let (buffer, memory, allocated_size) = create_buffer(instance, device, data, size_of::<T>() as vk::DeviceSize, usage, vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT)?;
+    let mapped = device.map_memory(memory, 0, vk::WHOLE_SIZE as vk::DeviceSize, vk::MemoryMapFlags::empty()).context("mapping buffer memory")?;
+    Ok((buffer, memory, allocated_size, mapped))
+}
+pub unsafe fn allocate_and_fill_buffer<T: Copy>(instance: &Instance, device: &Device, data: &VulkanApplicationData, elements: &[T], usage: vk::BufferUsageFlags)
+    -> anyhow::Result<(vk::Buffer, vk::DeviceMemory, vk::DeviceSize)> { //Host-visible, fine for small or frequently rewritten data.
+    let size = size_of_val(elements) as vk::DeviceSize;
+    let (buffer, memory, allocated_size) = create_buffer(instance, device, data, size, usage, vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT)?;
+    let mapped = device.map_memory(memory, 0, size, vk::MemoryMapFlags::empty()).context("mapping buffer memory")?;
+    copy_nonoverlapping(elements.as_ptr(), mapped.cast(), elements.len());
+    device.unmap_memory(memory);
+    Ok((buffer, memory, allocated_size))
+}
+pub unsafe fn create_device_local_buffer<T: Copy>(instance: &Instance, device: &Device, data: &VulkanApplicationData, elements: &[T], usage: vk::BufferUsageFlags)
+    -> anyhow::Result<(vk::Buffer, vk::DeviceMemory, vk::DeviceSize)> { //For static data, goes through a staging buffer and waits for the copy to finish.
+    let size = size_of_val(elements) as vk::DeviceSize;
+    let (staging_buffer, staging_memory, staging_allocated_size) = allocate_and_fill_buffer(instance, device, data, elements, vk::BufferUsageFlags::TRANSFER_SRC)?;
+    let (buffer, memory, allocated_size) = create_buffer(instance, device, data, size, vk::BufferUsageFlags::TRANSFER_DST | usage, vk::MemoryPropertyFlags::DEVICE_LOCAL)?;
+    let command_buffer = begin_single_time_commands(device, data)?;
+    let region = vk::BufferCopy::builder().src_offset(0).dst_offset(0).size(size);
+    device.cmd_copy_buffer(command_buffer, staging_buffer, buffer, &[region]);
+    PipelineBarrier::new().upload_buffer(buffer, Access::buffer_reads(usage)).execute(device, command_buffer);
+    end_single_time_commands(device, data, command_buffer)?;
+
+    destroy_buffer(device, staging_buffer, staging_memory, staging_allocated_size);
+    Ok((buffer, memory, allocated_size))
+}
+pub unsafe fn begin_single_time_commands(device: &Device, data: &VulkanApplicationData) -> anyhow::Result<vk::CommandBuffer> { //For one-off uploads, submitted and waited for with end_single_time_commands.
+    let allocate_info = vk::CommandBufferAllocateInfo::builder().command_pool(data.command_pool).level(vk::CommandBufferLevel::PRIMARY).command_buffer_count(1);
+    let command_buffer = device.allocate_command_buffers(&allocate_info).context("allocating single time command buffer")?[0];
+    let begin_info = vk::CommandBufferBeginInfo::builder().flags(vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT);
+    device.begin_command_buffer(command_buffer, &begin_info).context("beginning single time command buffer")?;
+    Ok(command_buffer)
+}
+pub unsafe fn end_single_time_commands(device: &Device, data: &VulkanApplicationData, command_buffer: vk::CommandBuffer) -> anyhow::Result<()> {
+    device.end_command_buffer(command_buffer).context("ending single time command buffer")?;
+    let command_buffers = &[command_buffer];
+    let submit_info = vk::SubmitInfo::builder().command_buffers(command_buffers);
+    device.queue_submit(data.graphics_queue, &[submit_info], vk::Fence::null()).context("submitting single time command buffer")?;
+    device.queue_wait_idle(data.graphics_queue).context("waiting for single time command buffer")?;
+
+    device.free_command_buffers(data.command_pool, command_buffers);
+    Ok(())
+}
+pub unsafe fn create_vertex_buffer<T: Copy>(instance: &Instance, device: &Device, data: &mut VulkanApplicationData, vertices: &[T]) -> anyhow::Result<()> { //T is Vertex or PackedVertex, matching the vertex layout the pipeline is built with.
+    data.vertex_buffer = None; //Frees the previous one, the GPU must be done with it.
+    data.vertex_buffer = Some(Arc::new(BufferResource::device_local(instance, device, data, vertices, vk::BufferUsageFlags::VERTEX_BUFFER)?));
+    Ok(())
+}
+pub unsafe fn create_index_buffer(instance: &Instance, device: &Device, data: &mut VulkanApplicationData, indices: &[u16]) -> anyhow::Result<()> { //Drawn with IndexType::UINT16.
+    data.index_buffer = None;
+    data.index_buffer = Some(Arc::new(BufferResource::device_local(instance, device, data, indices, vk::BufferUsageFlags::INDEX_BUFFER)?));
+    data.index_count = indices.len() as u32;
+    Ok(())
+}
+
+// Owns a buffer and its memory and frees both when dropped, so they can't be forgotten. Must be dropped before the device is destroyed.
+#[derive(Debug)]
+pub struct BufferResource {
+    device: Device,
+    buffer: vk::Buffer,
+    memory: vk::DeviceMemory,
+    allocated_size: vk::DeviceSize
+}
+impl BufferResource {
+    pub unsafe fn new(instance: &Instance, device: &Device, data: &VulkanApplicationData, size: vk::DeviceSize, usage: vk::BufferUsageFlags, properties: vk::MemoryPropertyFlags) -> anyhow::Result<Self> {
+        let (buffer, memory, allocated_size) = create_buffer(instance, device, data, size, usage, properties)?;
+        Ok(Self {device: device.clone(), buffer, memory, allocated_size})
+    }
+    pub unsafe fn device_local<T: Copy>(instance: &Instance, device: &Device, data: &VulkanApplicationData, elements: &[T], usage: vk::BufferUsageFlags) -> anyhow::Result<Self> { //See create_device_local_buffer.
+        let (buffer, memory, allocated_size) = create_device_local_buffer(instance, device, data, elements, usage)?;
+        Ok(Self {device: device.clone(), buffer, memory, allocated_size})
+    }
+    pub fn raw(&self) -> vk::Buffer {
+        self.buffer
+    }
+    pub fn memory(&self) -> vk::DeviceMemory {
+        self.memory
+    }
+    pub fn allocated_size(&self) -> vk::DeviceSize {
+        self.allocated_size
+    }
+}
+impl Drop for BufferResource {
+    fn drop(&mut self) { //The GPU must be done with the buffer, e.g. after waiting on the fence of the last submission using it.
+        unsafe { destroy_buffer(&self.device, self.buffer, self.memory, self.allocated_size) }
+    }
+}
+
+// A persistently mapped, host-visible buffer that grows as elements are appended, for data rebuilt every frame (debug lines, overlay quads, ...).
+// The GPU may still read the buffer while the CPU refills it, so use one DynamicBuffer per frame in flight.
+#[derive(Debug)]
+pub struct DynamicBuffer<T: Copy> {
+    buffer: vk::Buffer,
+    memory: vk::DeviceMemory,
+    allocated_size: vk::DeviceSize,
+    mapped: *mut T,
+    usage: vk::BufferUsageFlags,
+    capacity: usize,
+    len: usize
+}
+impl<T: Copy> DynamicBuffer<T> {
+    pub unsafe fn new(instance: &Instance, device: &Device, data: &VulkanApplicationData, usage: vk::BufferUsageFlags, capacity: usize) -> anyhow::Result<Self> {
+        let capacity = capacity.max(1);
+        let (buffer, memory, allocated_size) = create_buffer(instance, device, data, (capacity * size_of::<T>()) as vk::DeviceSize, usage,
+            vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT)?;
+        let mapped = device.map_memory(memory, 0, vk::WHOLE_SIZE as vk::DeviceSize, vk::MemoryMapFlags::empty()).context("mapping dynamic buffer memory")?.cast();
+        Ok(Self {buffer, memory, allocated_size, mapped, usage, capacity, len: 0})
+    }
+    pub fn clear(&mut self) {
+        self.len = 0;
+    }
+    pub unsafe fn extend(&mut self, instance: &Instance, device: &Device, data: &VulkanApplicationData, elements: &[T]) -> anyhow::Result<bool> { //Returns true when the buffer had to grow, command buffers using raw() must then be re-recorded.
+        let required_capacity = self.len + elements.len();
+        let grown = required_capacity > self.capacity;
+        if grown {
+            self.grow(instance, device, data, required_capacity.max(self.capacity * 2))?;
+        }
+        copy_nonoverlapping(elements.as_ptr(), self.mapped.add(self.len), elements.len());
+        self.len = required_capacity;
+        Ok(grown)
+    }
+    unsafe fn grow(&mut self, instance: &Instance, device: &Device, data: &VulkanApplicationData, capacity: usize) -> anyhow::Result<()> {
+        let mut grown = Self::new(instance, device, data, self.usage, capacity)?;
+        copy_nonoverlapping(self.mapped, grown.mapped, self.len);
+        grown.len = self.len;
+
+        device.device_wait_idle()?; //Frames still in flight may be reading the old buffer.
+        self.destroy(device);
+        *self = grown;
+        Ok(())
+    }
+    pub fn raw(&self) -> vk::Buffer {
+        self.buffer
+    }
+    pub fn len(&self) -> u32 { //Element count, as passed to the draw call.
+        self.len as u32
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+    pub unsafe fn destroy(&mut self, device: &Device) {
+        if self.buffer.is_null() {
+            return;
+        }
+        device.unmap_memory(self.memory);
+        destroy_buffer(device, self.buffer, self.memory, self.allocated_size);
+        self.buffer = vk::Buffer::null();
+        self.memory = vk::DeviceMemory::null();
+    }
+}
@@ -0,0 +1,209 @@
+use std::ptr::copy_nonoverlapping;
+use anyhow::anyhow;
+use cgmath::{ortho, perspective, point3, vec3, Deg, Matrix4, Rotation3, SquareMatrix};
+use vulkanalia::{Device, Instance, vk};
+use crate::graphical_core::{
+    MAX_FRAMES_IN_FLIGHT,
+    buffers::{allocate_buffer, destroy_buffer},
+    transform::{Quat, Transform},
+    vulkan_object::VulkanApplicationData
+};
+
+pub type Mat4 = Matrix4<f32>;
+
+// cgmath builds OpenGL style projections with a depth range of -1..1, Vulkan expects 0..1 (columns are listed one per line).
+const OPENGL_DEPTH_TO_VULKAN_DEPTH: Mat4 = Mat4::new(
+    1.0, 0.0, 0.0, 0.0,
+    0.0, 1.0, 0.0, 0.0,
+    0.0, 0.0, 0.5, 0.0,
+    0.0, 0.0, 0.5, 1.0,
+);
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct UniformBufferObject { //std140 layout, has to match the uniform block the shaders declare.
+    pub model: Mat4,
+    pub view: Mat4,
+    pub projection: Mat4
+}
+impl Default for UniformBufferObject {
+    fn default() -> Self {
+        Self {model: Mat4::identity(), view: Mat4::identity(), projection: Mat4::identity()}
+    }
+}
+
+const MODEL_DEGREES_PER_SECOND: f32 = 90.0;
+const MODEL_DEGREES_PER_MOUSE_UNIT: f32 = 0.25; //Horizontal mouse motion spins the model on top of its own rotation.
+const FIELD_OF_VIEW_DEGREES: f32 = 45.0;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ClipPlanes { //Distances from the camera, the defaults leave room for a large voxel world.
+    near: f32,
+    far: f32
+}
+impl ClipPlanes {
+    pub fn new(near: f32, far: f32) -> anyhow::Result<Self> {
+        if !(near > 0.0 && far > near) { //Written this way round so NaN is rejected too.
+            return Err(anyhow!("Clip planes need 0 < near < far, got near {} and far {}.", near, far));
+        }
+        Ok(Self {near, far})
+    }
+    pub fn near(&self) -> f32 {
+        self.near
+    }
+    pub fn far(&self) -> f32 {
+        self.far
+    }
+}
+impl Default for ClipPlanes {
+    fn default() -> Self {
+        Self {near: 0.1, far: 1000.0}
+    }
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct SceneState { //What the fixed-step update simulates, rendering blends the last two states.
+    pub model_degrees: f32
+}
+impl SceneState {
+    pub fn step(self, fixed_dt: f32, mouse_delta_x: f64) -> Self {
+        Self {model_degrees: (self.model_degrees + fixed_dt * MODEL_DEGREES_PER_SECOND + mouse_delta_x as f32 * MODEL_DEGREES_PER_MOUSE_UNIT) % 360.0}
+    }
+    pub fn interpolate(self, next: Self, factor: f32) -> Self { //factor 0 is self, 1 is next.
+        let mut delta = (next.model_degrees - self.model_degrees) % 360.0;
+        if delta > 180.0 {delta -= 360.0} else if delta < -180.0 {delta += 360.0} //The angle wraps, blend along the short way round.
+        Self {model_degrees: self.model_degrees + delta * factor}
+    }
+    pub fn model_transform(self) -> Transform { //Spins around the Z axis, in place at the origin.
+        Transform {rotation: Quat::from_angle_z(Deg(self.model_degrees)), ..Transform::default()}
+    }
+}
+
+pub fn compute_model_matrix(scene: SceneState) -> Mat4 {
+    scene.model_transform().matrix()
+}
+pub fn compute_view_matrix() -> Mat4 { //Looks down at the origin from above at an angle, with Z up.
+    Mat4::look_at_rh(point3(2.0, 2.0, 2.0), point3(0.0, 0.0, 0.0), vec3(0.0, 0.0, 1.0))
+}
+pub fn compute_projection_matrix(aspect_ratio: f32, clip_planes: ClipPlanes) -> Mat4 { //Logarithmic depth for even larger view distances isn't supported yet.
+    let mut projection = OPENGL_DEPTH_TO_VULKAN_DEPTH * perspective(Deg(FIELD_OF_VIEW_DEGREES), aspect_ratio, clip_planes.near, clip_planes.far);
+    projection[1][1] *= -1.0; //Vulkan's clip space Y points down, unlike OpenGL's, without this the image is upside down.
+    projection
+}
+pub fn compute_orthographic_matrix(left: f32, right: f32, bottom: f32, top: f32, near: f32, far: f32) -> anyhow::Result<Mat4> {
+    if left == right || bottom == top || near == far {
+        return Err(anyhow!("Orthographic projection needs a non-empty volume, got x: {}..{}, y: {}..{}, z: {}..{}.", left, right, bottom, top, near, far));
+    }
+    let mut projection = OPENGL_DEPTH_TO_VULKAN_DEPTH * ortho(left, right, bottom, top, near, far);
+    projection[1][1] *= -1.0; //Same Y flip as the perspective projection, so top ends up at the top of the window.
+    projection[3][1] *= -1.0;
+    Ok(projection)
+}
+pub fn compute_orthographic_matrix_from_size(width: f32, height: f32, near: f32, far: f32) -> anyhow::Result<Mat4> { //Centered on the view axis.
+    compute_orthographic_matrix(-width / 2.0, width / 2.0, -height / 2.0, height / 2.0, near, far)
+}
+pub unsafe fn create_uniform_buffers(instance: &Instance, device: &Device, data: &mut VulkanApplicationData) -> anyhow::Result<()> { //One per frame in flight, so the CPU never overwrites one the GPU is still reading.
+    for _ in 0..MAX_FRAMES_IN_FLIGHT {
+        let (buffer, memory, allocated_size, mapped) = allocate_buffer::<UniformBufferObject>(instance, device, data, vk::BufferUsageFlags::UNIFORM_BUFFER)?;
+        copy_nonoverlapping(&data.uniform_buffer_object, mapped.cast(), 1);
+        data.uniform_buffers.push(buffer);
+        data.uniform_buffers_memory.push(memory);
+        data.uniform_buffers_allocated_size.push(allocated_size);
+        data.uniform_buffers_ptr.push(mapped);
+    }
+    Ok(())
+}
+pub unsafe fn write_uniform_buffer(data: &VulkanApplicationData, frame: usize) { //The frame's previous submission must have finished.
+    copy_nonoverlapping(&data.uniform_buffer_object, data.uniform_buffers_ptr[frame].cast(), 1);
+}
+pub unsafe fn destroy_uniform_buffers(device: &Device, data: &mut VulkanApplicationData) { //Freeing the memory unmaps it as well.
+    for frame in 0..data.uniform_buffers.len() {
+        destroy_buffer(device, data.uniform_buffers[frame], data.uniform_buffers_memory[frame], data.uniform_buffers_allocated_size[frame]);
+    }
+    data.uniform_buffers.clear();
+    data.uniform_buffers_memory.clear();
+    data.uniform_buffers_allocated_size.clear();
+    data.uniform_buffers_ptr.clear();
+}
+
+#[cfg(test)]
+mod tests {
+    use cgmath::{vec4, InnerSpace, Vector4};
+    use super::*;
+
+    fn project(projection: &Mat4, x: f32, y: f32, z: f32) -> Vector4<f32> {
+        projection * vec4(x, y, z, 1.0)
+    }
+    fn assert_close(actual: Vector4<f32>, expected: Vector4<f32>) {
+        assert!((actual - expected).magnitude() < 1e-5, "{:?} != {:?}", actual, expected);
+    }
+
+    #[test]
+    fn scene_state_interpolates_between_steps() {
+        let previous = SceneState {model_degrees: 10.0};
+        let current = SceneState {model_degrees: 30.0};
+        assert_eq!(previous.interpolate(current, 0.0), previous);
+        assert_eq!(previous.interpolate(current, 0.5), SceneState {model_degrees: 20.0});
+        assert_eq!(previous.interpolate(current, 1.0), current);
+    }
+    #[test]
+    fn scene_state_interpolates_across_the_wrap() {
+        let blended = SceneState {model_degrees: 350.0}.interpolate(SceneState {model_degrees: 10.0}, 0.5);
+        assert!((blended.model_degrees - 360.0).abs() < 1e-4, "{:?}", blended);
+    }
+    #[test]
+    fn scene_state_steps_with_time_and_mouse() {
+        let stepped = SceneState::default().step(0.5, 40.0);
+        assert_eq!(stepped.model_degrees, 0.5 * MODEL_DEGREES_PER_SECOND + 40.0 * MODEL_DEGREES_PER_MOUSE_UNIT);
+    }
+    #[test]
+    fn model_matrix_comes_from_the_scene_transform() {
+        let model = compute_model_matrix(SceneState {model_degrees: 90.0});
+        assert_close(project(&model, 1.0, 0.0, 0.0), vec4(0.0, 1.0, 0.0, 1.0));
+        assert_close(project(&model, 0.0, 0.0, 1.0), vec4(0.0, 0.0, 1.0, 1.0));
+    }
+    #[test]
+    fn clip_planes_accept_a_valid_range() {
+        let clip_planes = ClipPlanes::new(0.5, 2000.0).unwrap();
+        assert_eq!((clip_planes.near(), clip_planes.far()), (0.5, 2000.0));
+    }
+    #[test]
+    fn clip_planes_reject_invalid_ranges() {
+        assert!(ClipPlanes::new(0.0, 100.0).is_err());
+        assert!(ClipPlanes::new(-1.0, 100.0).is_err());
+        assert!(ClipPlanes::new(10.0, 10.0).is_err());
+        assert!(ClipPlanes::new(10.0, 5.0).is_err());
+        assert!(ClipPlanes::new(f32::NAN, 5.0).is_err());
+    }
+    #[test]
+    fn perspective_maps_the_clip_planes_to_the_depth_range() {
+        let projection = compute_projection_matrix(1.0, ClipPlanes::new(1.0, 100.0).unwrap());
+        let near = project(&projection, 0.0, 0.0, -1.0);
+        let far = project(&projection, 0.0, 0.0, -100.0);
+        assert!((near.z / near.w).abs() < 1e-5 && (far.z / far.w - 1.0).abs() < 1e-5, "{:?} {:?}", near, far);
+    }
+    #[test]
+    fn orthographic_maps_the_box_center_to_the_origin() {
+        let projection = compute_orthographic_matrix(2.0, 6.0, -1.0, 3.0, 1.0, 5.0).unwrap();
+        assert_close(project(&projection, 4.0, 1.0, -3.0), vec4(0.0, 0.0, 0.5, 1.0));
+    }
+    #[test]
+    fn orthographic_maps_the_sides_to_the_edges_with_y_down() {
+        let projection = compute_orthographic_matrix(2.0, 6.0, -1.0, 3.0, 1.0, 5.0).unwrap();
+        assert_close(project(&projection, 2.0, 1.0, -3.0), vec4(-1.0, 0.0, 0.5, 1.0));
+        assert_close(project(&projection, 6.0, 1.0, -3.0), vec4(1.0, 0.0, 0.5, 1.0));
+        assert_close(project(&projection, 4.0, 3.0, -3.0), vec4(0.0, -1.0, 0.5, 1.0));
+        assert_close(project(&projection, 4.0, -1.0, -3.0), vec4(0.0, 1.0, 0.5, 1.0));
+    }
+    #[test]
+    fn orthographic_maps_near_to_zero_and_far_to_one() {
+        let projection = compute_orthographic_matrix(2.0, 6.0, -1.0, 3.0, 1.0, 5.0).unwrap();
+        assert_close(project(&projection, 4.0, 1.0, -1.0), vec4(0.0, 0.0, 0.0, 1.0));
+        assert_close(project(&projection, 4.0, 1.0, -5.0), vec4(0.0, 0.0, 1.0, 1.0));
+    }
+    #[test]
+    fn orthographic_rejects_empty_volumes() {
+        assert!(compute_orthographic_matrix(1.0, 1.0, -1.0, 1.0, 0.1, 10.0).is_err());
+        assert!(compute_orthographic_matrix(-1.0, 1.0, -1.0, 1.0, 2.0, 2.0).is_err());
+    }
+}
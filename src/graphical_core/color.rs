@@ -0,0 +1,46 @@
+use vulkanalia::vk;
+use crate::graphical_core::transform::Vec3;
+
+#[repr(transparent)]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct PackedColor(pub u32); //Four normalized bytes in R, G, B, A memory order, a quarter of the size of four floats.
+impl PackedColor {
+    pub const FORMAT: vk::Format = vk::Format::R8G8B8A8_UNORM; //The vertex attribute format, the shader still reads it as floats in 0..1.
+
+    pub fn from_rgba(rgba: [f32; 4]) -> Self { //Components outside 0..1 are clamped.
+        Self(u32::from_ne_bytes(rgba.map(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u8)))
+    }
+    pub fn from_rgb(rgb: Vec3) -> Self { //Opaque.
+        Self::from_rgba([rgb.x, rgb.y, rgb.z, 1.0])
+    }
+    pub fn to_rgba(self) -> [f32; 4] {
+        self.0.to_ne_bytes().map(|c| c as f32 / 255.0)
+    }
+    pub fn to_rgb(self) -> Vec3 {
+        let [r, g, b, _] = self.to_rgba();
+        Vec3::new(r, g, b)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn packed_color_round_trips_within_a_step() {
+        let rgba = [0.0, 0.2, 0.7, 1.0];
+        let unpacked = PackedColor::from_rgba(rgba).to_rgba();
+        for (original, round_tripped) in rgba.iter().zip(unpacked) {
+            assert!((original - round_tripped).abs() <= 1.0 / 255.0, "{:?} != {:?}", rgba, unpacked);
+        }
+    }
+    #[test]
+    fn packed_color_is_in_memory_order() {
+        let packed = PackedColor::from_rgba([1.0, 0.0, 0.0, 0.5]);
+        assert_eq!(packed.0.to_ne_bytes(), [255, 0, 0, 128]);
+    }
+    #[test]
+    fn packed_color_clamps() {
+        assert_eq!(PackedColor::from_rgba([-1.0, 2.0, 0.0, 1.0]).to_rgba(), [0.0, 1.0, 0.0, 1.0]);
+    }
+}
@@ -0,0 +1,25 @@
+use std::ffi::CStr;
+use vulkanalia::{Instance, vk};
+use vulkanalia::vk::{ExtDebugUtilsExtension, HasBuilder};
+use crate::VALIDATION_ENABLED;
+
+pub struct DebugLabel<'a> { //Names a section of a command buffer in GPU captures (RenderDoc etc.), the section ends when the label is dropped.
+    instance: &'a Instance,
+    command_buffer: vk::CommandBuffer
+}
+impl<'a> DebugLabel<'a> {
+    pub unsafe fn begin(instance: &'a Instance, command_buffer: vk::CommandBuffer, name: &CStr, color: [f32; 4]) -> Self {
+        if VALIDATION_ENABLED { //VK_EXT_debug_utils is only enabled together with validation.
+            let label = vk::DebugUtilsLabelEXT::builder().label_name(name.to_bytes_with_nul()).color(color);
+            instance.cmd_begin_debug_utils_label_ext(command_buffer, &label);
+        }
+        Self {instance, command_buffer}
+    }
+}
+impl Drop for DebugLabel<'_> {
+    fn drop(&mut self) {
+        if VALIDATION_ENABLED {
+            unsafe {self.instance.cmd_end_debug_utils_label_ext(self.command_buffer)};
+        }
+    }
+}
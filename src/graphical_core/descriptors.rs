@@ -0,0 +1,41 @@
+use std::mem::size_of;
+use anyhow::Context;
+use vulkanalia::{Device, vk};
+use vulkanalia::vk::{DeviceV1_0, HasBuilder};
+use crate::graphical_core::{
+    MAX_FRAMES_IN_FLIGHT,
+    camera::UniformBufferObject,
+    vulkan_object::VulkanApplicationData
+};
+
+const UNIFORM_BUFFER_BINDING: u32 = 0; //`layout(binding = 0) uniform UniformBufferObject` in shader.vert.
+
+pub unsafe fn create_descriptor_set_layout(device: &Device, data: &mut VulkanApplicationData) -> anyhow::Result<()> {
+    let uniform_binding = vk::DescriptorSetLayoutBinding::builder().binding(UNIFORM_BUFFER_BINDING).descriptor_type(vk::DescriptorType::UNIFORM_BUFFER)
+        .descriptor_count(1).stage_flags(vk::ShaderStageFlags::VERTEX);
+    let bindings = &[uniform_binding];
+    let info = vk::DescriptorSetLayoutCreateInfo::builder().bindings(bindings);
+    data.descriptor_set_layout = device.create_descriptor_set_layout(&info, None).context("creating descriptor set layout")?;
+    Ok(())
+}
+pub unsafe fn create_descriptor_pool(device: &Device, data: &mut VulkanApplicationData) -> anyhow::Result<()> { //Sized for exactly one set per frame in flight.
+    let uniform_size = vk::DescriptorPoolSize::builder().type_(vk::DescriptorType::UNIFORM_BUFFER).descriptor_count(MAX_FRAMES_IN_FLIGHT as u32);
+    let pool_sizes = &[uniform_size];
+    let info = vk::DescriptorPoolCreateInfo::builder().pool_sizes(pool_sizes).max_sets(MAX_FRAMES_IN_FLIGHT as u32);
+    data.descriptor_pool = device.create_descriptor_pool(&info, None).context("creating descriptor pool")?;
+    Ok(())
+}
+pub unsafe fn create_descriptor_sets(device: &Device, data: &mut VulkanApplicationData) -> anyhow::Result<()> { //Set i points at uniform buffer i, they are freed together with the pool.
+    let layouts = vec![data.descriptor_set_layout; MAX_FRAMES_IN_FLIGHT];
+    let info = vk::DescriptorSetAllocateInfo::builder().descriptor_pool(data.descriptor_pool).set_layouts(&layouts);
+    data.descriptor_sets = device.allocate_descriptor_sets(&info).context("allocating descriptor sets")?;
+
+    for (descriptor_set, uniform_buffer) in data.descriptor_sets.iter().zip(&data.uniform_buffers) {
+        let buffer_info = vk::DescriptorBufferInfo::builder().buffer(*uniform_buffer).offset(0).range(size_of::<UniformBufferObject>() as vk::DeviceSize);
+        let buffer_infos = &[buffer_info];
+        let write = vk::WriteDescriptorSet::builder().dst_set(*descriptor_set).dst_binding(UNIFORM_BUFFER_BINDING).dst_array_element(0)
+            .descriptor_type(vk::DescriptorType::UNIFORM_BUFFER).buffer_info(buffer_infos);
+        device.update_descriptor_sets(&[write], &[] as &[vk::CopyDescriptorSet]);
+    }
+    Ok(())
+}
@@ -1,19 +1,27 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::ffi::CStr;
 use std::os::raw::c_void;
-use anyhow::anyhow;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use anyhow::{anyhow, Context};
 use log::{debug, error, info, trace, warn};
 use thiserror::Error;
 use vulkanalia::{Device, Entry, Instance, vk};
-use vulkanalia::vk::{DeviceV1_0, EntryV1_0, ExtDebugUtilsExtension, Handle, HasBuilder};
+use vulkanalia::vk::{DeviceV1_0, EntryV1_0, ExtDebugUtilsExtension, Handle, HasBuilder, InstanceV1_0, KhrGetPhysicalDeviceProperties2Extension};
 use vulkanalia::window as vk_window;
 use winit::window::Window;
 use crate::{DEVICE_EXTENSIONS, graphical_core, PORTABILITY_MACOS_VERSION, VALIDATION_ENABLED, VALIDATION_LAYER};
 use crate::graphical_core::{
     MAX_FRAMES_IN_FLIGHT,
+    pass_list::{Pass, PassList, PassOutput},
+    render_pass::render_pass_clear_values,
+    test_pattern::test_pattern_regions,
     vulkan_object::VulkanApplicationData
 };
 
+const TEAR_LINE_WIDTH: u32 = 8;
+const BORDER_INDICATOR_WIDTH: u32 = 6;
+
 pub unsafe fn create_frame_buffers(device: &Device, data: &mut VulkanApplicationData) -> anyhow::Result<()> {
     data.framebuffers = data.swapchain_image_views.iter().map(|i| {
         let attachments = &[*i];
@@ -21,36 +29,84 @@ pub unsafe fn create_frame_buffers(device: &Device, data: &mut VulkanApplication
             .height(data.swapchain_accepted_images_width_and_height.height).layers(1);
 
         device.create_framebuffer(&create_info, None)
-    }).collect::<anyhow::Result<Vec<_>, _>>()?;
+    }).collect::<anyhow::Result<Vec<_>, _>>().context("creating framebuffers")?;
 
     Ok(())
 }
 pub unsafe fn create_command_pool(instance: &Instance, device: &Device, data: &mut VulkanApplicationData) -> anyhow::Result<()> {
     let indices = graphical_core::queue_families::RequiredQueueFamilies::get(instance, data, data.physical_device)?;
-    let info = vk::CommandPoolCreateInfo::builder().flags(vk::CommandPoolCreateFlags::empty()).queue_family_index(indices.graphics_queue_index);
+    let info = vk::CommandPoolCreateInfo::builder().flags(vk::CommandPoolCreateFlags::RESET_COMMAND_BUFFER).queue_family_index(indices.graphics_queue_index); //Lets single command buffers be reset and re-recorded.
 
-    data.command_pool = device.create_command_pool(&info, None)?;
+    data.command_pool = device.create_command_pool(&info, None).context("creating command pool")?;
+    Ok(())
+}
+pub unsafe fn create_command_buffers(instance: &Instance, device: &Device, data: &mut VulkanApplicationData) -> anyhow::Result<()> {
+    if data.command_buffers.len() != data.framebuffers.len() { //Existing buffers are reset and re-recorded, they only get reallocated when the swapchain image count changes.
+        if !data.command_buffers.is_empty() {
+            device.free_command_buffers(data.command_pool, &data.command_buffers);
+        }
+        let allocate_info = vk::CommandBufferAllocateInfo::builder().command_pool(data.command_pool).level(vk::CommandBufferLevel::PRIMARY).command_buffer_count(data.framebuffers.len() as u32);
+        data.command_buffers = device.allocate_command_buffers(&allocate_info).context("allocating command buffers")?;
+    }
+    data.command_buffers_outdated = vec![false; data.command_buffers.len()];
+    data.command_buffer_frames = vec![0; data.command_buffers.len()];
+    for image_index in 0..data.command_buffers.len() {
+        record_command_buffer(instance, device, data, image_index, 0)?;
+    }
     Ok(())
 }
-pub unsafe fn create_command_buffers(device: &Device, data: &mut VulkanApplicationData) -> anyhow::Result<()> {
-    let allocate_info = vk::CommandBufferAllocateInfo::builder().command_pool(data.command_pool).level(vk::CommandBufferLevel::PRIMARY).command_buffer_count(data.framebuffers.len() as u32);
-    data.command_buffers = device.allocate_command_buffers(&allocate_info)?;
-    for (i, command_buffer) in data.command_buffers.iter().enumerate() {
-        let info = vk::CommandBufferBeginInfo::builder();
+pub unsafe fn record_command_buffer(instance: &Instance, device: &Device, data: &VulkanApplicationData, image_index: usize, frame: usize) -> anyhow::Result<()> { //The command buffer must not be in use by the GPU. Binds the descriptor set of the given frame in flight.
+    let command_buffer = data.command_buffers[image_index];
+    device.reset_command_buffer(command_buffer, vk::CommandBufferResetFlags::empty()).context("resetting command buffer")?;
 
-        device.begin_command_buffer(*command_buffer, &info)?;
+    let info = vk::CommandBufferBeginInfo::builder();
 
-        let render_area = vk::Rect2D::builder().offset(vk::Offset2D::default()).extent(data.swapchain_accepted_images_width_and_height); //Size of the area that will be rendered to.
-        let color_clear_value = vk::ClearValue {color: vk::ClearColorValue {float32: [0.0, 0.0, 0.0, 1.0]}}; //Black screen that replaces the screen between each shown frame.
-        let clear_values = &[color_clear_value];
-        let info = vk::RenderPassBeginInfo::builder().render_pass(data.render_pass).framebuffer(data.framebuffers[i]).render_area(render_area).clear_values(clear_values); //Attach previous constructions to a render pass object.
+    device.begin_command_buffer(command_buffer, &info).context("beginning command buffer recording")?;
 
-        device.cmd_begin_render_pass(*command_buffer, &info, vk::SubpassContents::INLINE);
-        device.cmd_bind_pipeline(*command_buffer, vk::PipelineBindPoint::GRAPHICS, data.pipeline);
-        device.cmd_draw(*command_buffer, 3, 1, 0, 0);
-        device.cmd_end_render_pass(*command_buffer);
-        device.end_command_buffer(*command_buffer)?;
-    }
+    let render_area = vk::Rect2D::builder().offset(vk::Offset2D::default()).extent(data.swapchain_accepted_images_width_and_height); //Size of the area that will be rendered to.
+    let clear_values = render_pass_clear_values(data);
+    let info = vk::RenderPassBeginInfo::builder().render_pass(data.render_pass).framebuffer(data.framebuffers[image_index]).render_area(render_area).clear_values(&clear_values); //Attach previous constructions to a render pass object.
+
+    let opaque_pass = Pass::new(c"Opaque Pass", None, PassOutput::Swapchain, |command_buffer| unsafe { //Further passes (shadow, overlay, post) get added to the list after it.
+        device.cmd_begin_render_pass(command_buffer, &info, vk::SubpassContents::INLINE);
+        device.cmd_bind_pipeline(command_buffer, vk::PipelineBindPoint::GRAPHICS, data.pipeline);
+        let extent = data.swapchain_accepted_images_width_and_height;
+        let viewport = vk::Viewport::builder().x(0.0).y(0.0).width(extent.width as f32).height(extent.height as f32).min_depth(0.0).max_depth(1.0);
+        device.cmd_set_viewport(command_buffer, 0, &[viewport]); //Dynamic state, so the pipeline survives swapchain resizes.
+        device.cmd_set_scissor(command_buffer, 0, &[render_area]);
+        device.cmd_push_constants(command_buffer, data.pipeline_layout, vk::ShaderStageFlags::FRAGMENT, 0, &(data.render_settings.debug_winding as u32).to_ne_bytes());
+        if data.render_settings.test_pattern_enabled {
+            for (color, rects) in test_pattern_regions(extent) {
+                let attachment = vk::ClearAttachment::builder().aspect_mask(vk::ImageAspectFlags::COLOR).color_attachment(0).clear_value(vk::ClearValue {color: vk::ClearColorValue {float32: color}});
+                device.cmd_clear_attachments(command_buffer, &[attachment], &rects);
+            }
+        } else if let (Some(vertex_buffer), Some(index_buffer)) = (&data.vertex_buffer, &data.index_buffer) {
+            device.cmd_bind_vertex_buffers(command_buffer, 0, &[vertex_buffer.raw()], &[0]);
+            device.cmd_bind_index_buffer(command_buffer, index_buffer.raw(), 0, vk::IndexType::UINT16);
+            device.cmd_bind_descriptor_sets(command_buffer, vk::PipelineBindPoint::GRAPHICS, data.pipeline_layout, 0, &[data.descriptor_sets[frame]], &[]);
+            device.cmd_draw_indexed(command_buffer, data.index_count, 1, 0, 0, 0);
+        }
+        if let Some(color) = data.render_settings.border_indicator {
+            let attachment = vk::ClearAttachment::builder().aspect_mask(vk::ImageAspectFlags::COLOR).color_attachment(0).clear_value(vk::ClearValue {color: vk::ClearColorValue {float32: color}});
+            let thickness = BORDER_INDICATOR_WIDTH.min(extent.width / 2).min(extent.height / 2).max(1);
+            let rect = |x: u32, y: u32, width: u32, height: u32| vk::ClearRect::builder()
+                .rect(vk::Rect2D {offset: vk::Offset2D {x: x as i32, y: y as i32}, extent: vk::Extent2D {width, height}}).base_array_layer(0).layer_count(1);
+            let rects = [rect(0, 0, extent.width, thickness), rect(0, extent.height - thickness, extent.width, thickness),
+                rect(0, 0, thickness, extent.height), rect(extent.width - thickness, 0, thickness, extent.height)];
+            device.cmd_clear_attachments(command_buffer, &[attachment], &rects);
+        }
+        if let Some(position) = data.render_settings.tear_line_position.filter(|position| *position < extent.width) { //Can be past the edge right after the swapchain shrank.
+            let attachment = vk::ClearAttachment::builder().aspect_mask(vk::ImageAspectFlags::COLOR).color_attachment(0)
+                .clear_value(vk::ClearValue {color: vk::ClearColorValue {float32: [1.0, 1.0, 1.0, 1.0]}});
+            let bar = vk::Rect2D::builder().offset(vk::Offset2D {x: position as i32, y: 0})
+                .extent(vk::Extent2D {width: TEAR_LINE_WIDTH.min(extent.width - position), height: extent.height});
+            let rect = vk::ClearRect::builder().rect(bar).base_array_layer(0).layer_count(1);
+            device.cmd_clear_attachments(command_buffer, &[attachment], &[rect]);
+        }
+        device.cmd_end_render_pass(command_buffer);
+    });
+    PassList::new().add(opaque_pass).execute(instance, device, command_buffer);
+    device.end_command_buffer(command_buffer).context("ending command buffer recording")?;
     Ok(())
 }
 pub unsafe fn create_sync_objects(device: &Device, data: &mut VulkanApplicationData) -> anyhow::Result<()> {
@@ -58,23 +114,37 @@ pub unsafe fn create_sync_objects(device: &Device, data: &mut VulkanApplicationD
     let fence_info = vk::FenceCreateInfo::builder().flags(vk::FenceCreateFlags::SIGNALED);
 
     for _ in 0..MAX_FRAMES_IN_FLIGHT {
-        data.image_available_semaphores.push(device.create_semaphore(&semaphore_info, None)?);
-        data.render_finished_semaphores.push(device.create_semaphore(&semaphore_info, None)?);
-        data.in_flight_fences.push(device.create_fence(&fence_info, None)?);
+        data.image_available_semaphores.push(device.create_semaphore(&semaphore_info, None).context("creating image available semaphore")?);
+        data.render_finished_semaphores.push(device.create_semaphore(&semaphore_info, None).context("creating render finished semaphore")?);
+        if !data.timeline_semaphore_enabled {
+            data.in_flight_fences.push(device.create_fence(&fence_info, None).context("creating in-flight fence")?);
+        }
+    }
+    if data.timeline_semaphore_enabled { //Replaces the in-flight fences, acquire and present still need the binary semaphores.
+        let mut type_info = vk::SemaphoreTypeCreateInfo::builder().semaphore_type(vk::SemaphoreType::TIMELINE).initial_value(0);
+        let timeline_info = vk::SemaphoreCreateInfo::builder().push_next(&mut type_info);
+        data.frame_timeline_semaphore = device.create_semaphore(&timeline_info, None).context("creating frame timeline semaphore")?;
+        data.frame_timeline_values = vec![0; MAX_FRAMES_IN_FLIGHT];
     }
     data.images_in_flight = data.swapchain_images.iter().map(|_| vk::Fence::null()).collect();
+    data.image_timeline_values = vec![0; data.swapchain_images.len()];
     Ok(())
 }
 pub unsafe fn create_instance(window: &Window, entry: &Entry, data: &mut VulkanApplicationData) -> anyhow::Result<Instance> {
 
+    let config = &data.config;
+    let instance_version = entry.version()?;
+    if (config.api_version.major, config.api_version.minor) > (instance_version.major, instance_version.minor) {
+        return Err(anyhow!("Requested Vulkan API version {} but the instance only supports {}.", config.api_version, instance_version));
+    }
     let application_info = vk::ApplicationInfo::builder()
-        .application_name(b"Vulkan Tutorial\0")
-        .application_version(vk::make_version(1, 0, 0))
-        .engine_name(b"No Engine\0")
-        .engine_version(vk::make_version(1, 0, 0))
-        .api_version(vk::make_version(1, 0, 0));
+        .application_name(config.application_name.as_bytes_with_nul())
+        .application_version(config.application_version.into())
+        .engine_name(config.engine_name.as_bytes_with_nul())
+        .engine_version(config.engine_version.into())
+        .api_version(config.api_version.into());
 
-    let available_layers = entry.enumerate_instance_layer_properties()?.iter().map(|l| l.layer_name).collect::<HashSet<_>>();
+    let available_layers = entry.enumerate_instance_layer_properties().context("enumerating instance layers")?.iter().map(|l| l.layer_name).collect::<HashSet<_>>();
 
     if VALIDATION_ENABLED && !available_layers.contains(&VALIDATION_LAYER) {
         return Err(anyhow!("Validation layer requested but not supported."));
@@ -89,47 +159,99 @@ pub unsafe fn create_instance(window: &Window, entry: &Entry, data: &mut VulkanA
     // Adding extensions
     let mut extensions = vk_window::get_required_instance_extensions(window).iter().map(|e| e.as_ptr()).collect::<Vec<_>>();
 
-// Required by Vulkan SDK on macOS since 1.3.216.
+// Required by Vulkan SDK on macOS since 1.3.216, and by any other setup that only exposes portability devices.
     //__________________________________________________________________________________________________________________________//
+    let available_extensions = entry.enumerate_instance_extension_properties(None).context("enumerating instance extensions")?.iter().map(|e| e.extension_name).collect::<HashSet<_>>();
+    let portability_extensions_available = available_extensions.contains(&vk::KHR_PORTABILITY_ENUMERATION_EXTENSION.name)
+        && available_extensions.contains(&vk::KHR_GET_PHYSICAL_DEVICE_PROPERTIES2_EXTENSION.name);
     let flags = if
-    cfg!(target_os = "macos") &&
-        entry.version()? >= PORTABILITY_MACOS_VERSION
+    (cfg!(target_os = "macos") &&
+        entry.version()? >= PORTABILITY_MACOS_VERSION) || portability_extensions_available
     {
-        info!("Enabling extensions for macOS portability.");
-        extensions.push(vk::KHR_GET_PHYSICAL_DEVICE_PROPERTIES2_EXTENSION.name.as_ptr());
+        info!("Enabling extensions for portability enumeration.");
         extensions.push(vk::KHR_PORTABILITY_ENUMERATION_EXTENSION.name.as_ptr());
         vk::InstanceCreateFlags::ENUMERATE_PORTABILITY_KHR
     } else {
         vk::InstanceCreateFlags::empty()
     };
+    // Required by portability enumeration, and used to query extension-provided GPU information such as the memory budget.
+    if flags.contains(vk::InstanceCreateFlags::ENUMERATE_PORTABILITY_KHR) || available_extensions.contains(&vk::KHR_GET_PHYSICAL_DEVICE_PROPERTIES2_EXTENSION.name) {
+        extensions.push(vk::KHR_GET_PHYSICAL_DEVICE_PROPERTIES2_EXTENSION.name.as_ptr());
+        data.physical_device_properties2_enabled = true;
+    }
     if VALIDATION_ENABLED {
         extensions.push(vk::EXT_DEBUG_UTILS_EXTENSION.name.as_ptr());
     }
+    data.portability_enabled = flags.contains(vk::InstanceCreateFlags::ENUMERATE_PORTABILITY_KHR);
+    data.enabled_instance_layers = layers.iter().map(|l| CStr::from_ptr(*l).to_string_lossy().into_owned()).collect();
+    data.enabled_instance_extensions = extensions.iter().map(|e| CStr::from_ptr(*e).to_string_lossy().into_owned()).collect();
     //__________________________________________________________________________________________________________________________//
 
     let mut info = vk::InstanceCreateInfo::builder().application_info(&application_info).enabled_layer_names(&layers).enabled_extension_names(&extensions).flags(flags);
 
     let mut debug_info = vk::DebugUtilsMessengerCreateInfoEXT::builder().message_severity(vk::DebugUtilsMessageSeverityFlagsEXT::all())
         .message_type(vk::DebugUtilsMessageTypeFlagsEXT::all()).user_callback(Some(debug_callback));
+    debug_info.user_data = Arc::as_ptr(&data.debug_callback_state) as *mut c_void; //The state is kept alive by the application data until after the instance is destroyed.
 
     if VALIDATION_ENABLED {
 
         info = info.push_next(&mut debug_info);
     }
-    let instance = entry.create_instance(&info, None)?;
+    let instance = entry.create_instance(&info, None).context("creating instance")?;
 
     // Messenger
 
     if VALIDATION_ENABLED {
-        data.debug_messenger = instance.create_debug_utils_messenger_ext(&debug_info, None)?;
+        data.debug_messenger = instance.create_debug_utils_messenger_ext(&debug_info, None).context("creating debug messenger")?;
     }
 
     Ok(instance)
 }
+#[derive(Clone, Debug)]
+pub struct CapturedMessage {
+    pub severity: vk::DebugUtilsMessageSeverityFlagsEXT,
+    pub message_type: vk::DebugUtilsMessageTypeFlagsEXT,
+    pub message: String
+}
+#[derive(Debug)]
+pub struct DebugCallbackState { //Shared with the debug callback through its user data pointer.
+    shown_message_types: AtomicU32,
+    capture_messages: AtomicBool,
+    captured_messages: Mutex<Vec<CapturedMessage>>
+}
+impl DebugCallbackState {
+    pub fn set_message_capture(&self, enabled: bool) {
+        self.capture_messages.store(enabled, Ordering::Relaxed);
+    }
+    pub fn captured_messages(&self) -> Vec<CapturedMessage> {
+        self.captured_messages.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).clone()
+    }
+    pub fn clear_captured_messages(&self) {
+        self.captured_messages.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).clear();
+    }
+    pub fn set_shown_message_types(&self, message_types: vk::DebugUtilsMessageTypeFlagsEXT) {
+        self.shown_message_types.store(message_types.bits(), Ordering::Relaxed);
+    }
+    pub fn shown_message_types(&self) -> vk::DebugUtilsMessageTypeFlagsEXT {
+        vk::DebugUtilsMessageTypeFlagsEXT::from_bits_truncate(self.shown_message_types.load(Ordering::Relaxed))
+    }
+}
+impl Default for DebugCallbackState {
+    fn default() -> Self {
+        Self {shown_message_types: AtomicU32::new(vk::DebugUtilsMessageTypeFlagsEXT::all().bits()), capture_messages: AtomicBool::new(false), captured_messages: Mutex::new(Vec::new())}
+    }
+}
 pub extern "system" fn debug_callback(severity: vk::DebugUtilsMessageSeverityFlagsEXT, type_: vk::DebugUtilsMessageTypeFlagsEXT,
-                                  data: *const vk::DebugUtilsMessengerCallbackDataEXT, _: *mut c_void, ) -> vk::Bool32 {
+                                  data: *const vk::DebugUtilsMessengerCallbackDataEXT, user_data: *mut c_void, ) -> vk::Bool32 {
+    let state = unsafe { (user_data as *const DebugCallbackState).as_ref() };
+    if state.is_some_and(|state| !state.shown_message_types().intersects(type_)) {
+        return vk::FALSE;
+    }
     let data = unsafe { *data };
     let message = unsafe { CStr::from_ptr(data.message) }.to_string_lossy();
+    if let Some(state) = state.filter(|state| state.capture_messages.load(Ordering::Relaxed)) {
+        state.captured_messages.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).push(CapturedMessage {severity, message_type: type_, message: message.to_string()});
+    }
 
     if severity >= vk::DebugUtilsMessageSeverityFlagsEXT::ERROR {
         error!("({:?}) {}", type_, message);
@@ -148,13 +270,19 @@ pub extern "system" fn debug_callback(severity: vk::DebugUtilsMessageSeverityFla
 pub struct SuitabilityError(pub &'static str);
 pub unsafe fn create_logical_device(entry: &Entry, instance: &Instance, data: &mut VulkanApplicationData) -> anyhow::Result<Device> {
     let indices = graphical_core::queue_families::RequiredQueueFamilies::get(instance, data, data.physical_device)?;
-    let mut unique_indices = HashSet::new();
+    let config = &data.config;
+    for (queue, priority) in [("graphics", config.graphics_queue_priority), ("presentation", config.presentation_queue_priority)] {
+        if !(0.0..=1.0).contains(&priority) {
+            return Err(anyhow!("The {} queue priority must be between 0 and 1, got {}.", queue, priority));
+        }
+    }
+    let mut family_priorities = HashMap::new(); //Family index to queue priority, a family shared by both queues only gets one queue.
 
-    unique_indices.insert(indices.graphics_queue_index);
-    unique_indices.insert(indices.presentation_queue_index);
+    family_priorities.insert(indices.graphics_queue_index, [config.graphics_queue_priority]);
+    let presentation_priority = family_priorities.get(&indices.presentation_queue_index).map_or(config.presentation_queue_priority, |p| p[0].max(config.presentation_queue_priority));
+    family_priorities.insert(indices.presentation_queue_index, [presentation_priority]);
 
-    let queue_priorities = &[1.0];
-    let queue_infos = unique_indices.iter().map(|i| {vk::DeviceQueueCreateInfo::builder().queue_family_index(*i).queue_priorities(queue_priorities)}).collect::<Vec<_>>();
+    let queue_infos = family_priorities.iter().map(|(i, priorities)| {vk::DeviceQueueCreateInfo::builder().queue_family_index(*i).queue_priorities(priorities)}).collect::<Vec<_>>();
 
     let layers = if VALIDATION_ENABLED {
         vec![VALIDATION_LAYER.as_ptr()]
@@ -163,16 +291,71 @@ pub unsafe fn create_logical_device(entry: &Entry, instance: &Instance, data: &m
     };
     let mut extensions = DEVICE_EXTENSIONS.iter().map(|n| n.as_ptr()).collect::<Vec<_>>();
 
-    // Required by Vulkan SDK on macOS since 1.3.216.
-    if cfg!(target_os = "macos") && entry.version()? >= PORTABILITY_MACOS_VERSION {
+    // Required by Vulkan SDK on macOS since 1.3.216, and by every GPU that reports itself as a portability device.
+    let available_extensions = instance.enumerate_device_extension_properties(data.physical_device, None).context("enumerating device extensions")?.iter().map(|e| e.extension_name).collect::<HashSet<_>>();
+    if (cfg!(target_os = "macos") && entry.version()? >= PORTABILITY_MACOS_VERSION) || available_extensions.contains(&vk::KHR_PORTABILITY_SUBSET_EXTENSION.name) {
         extensions.push(vk::KHR_PORTABILITY_SUBSET_EXTENSION.name.as_ptr());
     }
-    let features = vk::PhysicalDeviceFeatures::builder();
-    let info = vk::DeviceCreateInfo::builder().queue_create_infos(&queue_infos).enabled_layer_names(&layers).enabled_extension_names(&extensions).enabled_features(&features);
-    let device = instance.create_device(data.physical_device, &info, None)?;
+    if data.physical_device_properties2_enabled && available_extensions.contains(&vk::EXT_MEMORY_BUDGET_EXTENSION.name) {
+        extensions.push(vk::EXT_MEMORY_BUDGET_EXTENSION.name.as_ptr());
+        data.memory_budget_enabled = true;
+    }
+    if data.physical_device_properties2_enabled && available_extensions.contains(&vk::KHR_DRIVER_PROPERTIES_EXTENSION.name) {
+        extensions.push(vk::KHR_DRIVER_PROPERTIES_EXTENSION.name.as_ptr());
+        data.driver_properties_enabled = true;
+    }
+    if available_extensions.contains(&vk::KHR_INCREMENTAL_PRESENT_EXTENSION.name) {
+        extensions.push(vk::KHR_INCREMENTAL_PRESENT_EXTENSION.name.as_ptr());
+        data.incremental_present_enabled = true;
+    }
+    if data.config.prefer_timeline_semaphore && data.physical_device_properties2_enabled && available_extensions.contains(&vk::KHR_TIMELINE_SEMAPHORE_EXTENSION.name) {
+        let mut timeline_semaphore_features = vk::PhysicalDeviceTimelineSemaphoreFeatures::default();
+        let mut supported_features = vk::PhysicalDeviceFeatures2::builder().push_next(&mut timeline_semaphore_features);
+        instance.get_physical_device_features2_khr(data.physical_device, &mut supported_features);
+        if timeline_semaphore_features.timeline_semaphore == vk::TRUE {
+            extensions.push(vk::KHR_TIMELINE_SEMAPHORE_EXTENSION.name.as_ptr());
+            data.timeline_semaphore_enabled = true;
+        }
+    }
+    if data.config.prefer_timeline_semaphore && !data.timeline_semaphore_enabled {
+        warn!("Timeline semaphores aren't supported, falling back to fences for frame synchronization.");
+    }
+    data.enabled_device_extensions = extensions.iter().map(|e| CStr::from_ptr(*e).to_string_lossy().into_owned()).collect();
+    data.sample_rate_shading_enabled = instance.get_physical_device_features(data.physical_device).sample_rate_shading == vk::TRUE;
+    let features = vk::PhysicalDeviceFeatures::builder().sample_rate_shading(data.sample_rate_shading_enabled);
+    let mut timeline_semaphore_features = vk::PhysicalDeviceTimelineSemaphoreFeatures::builder().timeline_semaphore(true);
+    let mut info = vk::DeviceCreateInfo::builder().queue_create_infos(&queue_infos).enabled_layer_names(&layers).enabled_extension_names(&extensions).enabled_features(&features);
+    if data.timeline_semaphore_enabled {
+        info = info.push_next(&mut timeline_semaphore_features);
+    }
+    let device = instance.create_device(data.physical_device, &info, None).context("creating logical device")?;
 
     data.graphics_queue = device.get_device_queue(indices.graphics_queue_index, 0);
     data.presentation_queue = device.get_device_queue(indices.presentation_queue_index, 0);
 
     Ok(device)
-}
\ No newline at end of file
+}
+pub unsafe fn log_startup_summary(entry: &Entry, instance: &Instance, data: &VulkanApplicationData) { //Called once after device creation, meant for debugging driver and ICD setup issues. Only logs, never fails.
+    let device_info = graphical_core::gpu::get_device_info(instance, data);
+    info!("Vulkan setup:");
+    match entry.version() {
+        Ok(version) => info!("  Instance version: {}", version),
+        Err(error) => warn!("  Instance version: couldn't be queried ({})", error)
+    }
+    info!("  API version: requested {}, negotiated {}", data.config.api_version, graphical_core::gpu::negotiated_api_version(instance, data));
+    info!("  Instance layers: {:?}", data.enabled_instance_layers);
+    info!("  Instance extensions: {:?}", data.enabled_instance_extensions);
+    info!("  Portability enumeration: {}", data.portability_enabled);
+    info!("  Device: `{}` ({:?}), Vulkan {}", device_info.name, device_info.device_type, device_info.api_version);
+    match &device_info.driver {
+        Some(driver) => info!("  Driver: {} ({:?}) {}, conformance {}.{}.{}.{}", driver.name, driver.id, driver.info, driver.conformance_version.major,
+            driver.conformance_version.minor, driver.conformance_version.subminor, driver.conformance_version.patch),
+        None => info!("  Driver version: {:#x}", device_info.driver_version)
+    }
+    info!("  Device extensions: {:?}", data.enabled_device_extensions);
+    info!("  Frame synchronization: {}", if data.timeline_semaphore_enabled {"timeline semaphore"} else {"fences"});
+    match graphical_core::queue_families::RequiredQueueFamilies::get(instance, data, data.physical_device) {
+        Ok(indices) => info!("  Queue families: graphics {}, presentation {}", indices.graphics_queue_index, indices.presentation_queue_index),
+        Err(error) => warn!("  Queue families: couldn't be queried ({})", error)
+    }
+}
@@ -1,9 +1,9 @@
 use std::collections::HashSet;
-use anyhow::anyhow;
+use anyhow::{anyhow, Context};
 use log::{info, warn};
 use thiserror::Error;
-use vulkanalia::Instance;
-use vulkanalia::vk::{InstanceV1_0, PhysicalDevice, PhysicalDeviceProperties};
+use vulkanalia::{Instance, Version, vk};
+use vulkanalia::vk::{HasBuilder, InstanceV1_0, KhrGetPhysicalDeviceProperties2Extension, PhysicalDevice, PhysicalDeviceProperties, PhysicalDeviceType};
 use crate::DEVICE_EXTENSIONS;
 use crate::graphical_core::{vulkan_object::VulkanApplicationData, queue_families::RequiredQueueFamilies};
 
@@ -11,11 +11,37 @@ use crate::graphical_core::{vulkan_object::VulkanApplicationData, queue_families
 #[error("Missing {0}.")]
 pub struct SuitabilityError(pub &'static str);
 
+#[derive(Clone, Debug)]
+pub struct DeviceInfo {
+    pub name: String,
+    pub device_type: vk::PhysicalDeviceType,
+    pub api_version: Version,
+    pub driver_version: u32, //Vendor specific encoding.
+    pub driver: Option<DriverInfo> //None when VK_KHR_driver_properties can't be queried, the 1.0 properties above are all there is then.
+}
+#[derive(Clone, Debug)]
+pub struct DriverInfo {
+    pub id: vk::DriverId,
+    pub name: String,
+    pub info: String,
+    pub conformance_version: vk::ConformanceVersion
+}
+
 pub unsafe fn choose_gpu(current_system: &Instance, vulkan_application_data: &mut VulkanApplicationData) -> anyhow::Result<()> {
-    for gpu in all_available_gpus(current_system)? {
+    let mut gpus = all_available_gpus(current_system).context("enumerating physical devices")?;
+    info!("Found {} Vulkan device(s).", gpus.len());
+    if gpus.is_empty() { //Usually no ICD is installed or found, which is a different problem than no suitable GPU.
+        return Err(anyhow!("No Vulkan-capable devices found, check your ICD/driver installation."));
+    }
+    if vulkan_application_data.config.deterministic_gpu_selection {
+        sort_gpus_deterministically(current_system, &mut gpus);
+    }
+    let prefer_software_gpu = vulkan_application_data.config.prefer_software_gpu;
+    gpus.sort_by_key(|gpu| is_software_gpu(&get_gpu_properties(current_system, *gpu)) != prefer_software_gpu); //Stable, so the deterministic order is kept within each group.
+    for gpu in gpus {
         let gpu_properties = get_gpu_properties(current_system, gpu);
-        if gpu_not_have_required_properties(current_system, vulkan_application_data, gpu) {
-            warn!("Skipping GPU (`{}.`)", gpu_properties.device_name);
+        if let Err(error) = check_gpu(current_system, vulkan_application_data, gpu) {
+            warn!("Skipping GPU (`{}`): {}", gpu_properties.device_name, error);
         } else {
             info!("Selected GPU (`{}`).", gpu_properties.device_name);
             vulkan_application_data.physical_device = gpu;
@@ -28,8 +54,8 @@ pub unsafe fn check_gpu(current_system: &Instance, vulkan_application_data: &Vul
     RequiredQueueFamilies::get(current_system, vulkan_application_data, gpu)?;
     check_gpu_extensions(current_system, gpu)?;
     let support = crate::graphical_core::swapchain::SwapchainSupport::get(current_system, vulkan_application_data, gpu)?;
-    if support.formats.is_empty() || support.present_modes.is_empty() {
-        return Err(anyhow!(SuitabilityError("Insufficient swapchain support.")));
+    if let Some(reason) = support.inadequacy_reason() {
+        return Err(anyhow!(SuitabilityError(reason)));
     }
     Ok(())
 }
@@ -41,17 +67,41 @@ pub unsafe fn check_gpu_extensions(instance: &Instance, physical_device: Physica
         Err(anyhow!(SuitabilityError("Missing required GPU extensions.")))
     }
 }
+fn is_software_gpu(properties: &PhysicalDeviceProperties) -> bool { //Mesa's lavapipe reports itself as an llvmpipe CPU device.
+    let name = properties.device_name.to_string().to_lowercase();
+    properties.device_type == PhysicalDeviceType::CPU || name.contains("llvmpipe") || name.contains("lavapipe")
+}
 unsafe fn all_available_gpus(current_system: &Instance) -> crate::VkResult<Vec<PhysicalDevice>> {
     current_system.enumerate_physical_devices()
 }
+/// Orders GPUs by device name, then by pipeline cache UUID, so the enumeration order no longer depends on the driver.
+/// This only stabilizes which of several equally suitable GPUs is picked, it never changes which GPUs are suitable.
+unsafe fn sort_gpus_deterministically(current_system: &Instance, gpus: &mut [PhysicalDevice]) {
+    gpus.sort_by_cached_key(|gpu| {
+        let gpu_properties = get_gpu_properties(current_system, *gpu);
+        (gpu_properties.device_name.to_string(), gpu_properties.pipeline_cache_uuid)
+    });
+}
 unsafe fn get_gpu_properties(current_system: &Instance, gpu: PhysicalDevice) -> PhysicalDeviceProperties {
     current_system.get_physical_device_properties(gpu)
 }
-unsafe fn gpu_not_have_required_properties(current_system: &Instance, vulkan_application_data: &VulkanApplicationData, gpu: PhysicalDevice) -> bool  {
-    if let Err(_) = check_gpu(current_system, vulkan_application_data, gpu) {
-        true
-    }
-    else {
-        false
-    }
-}
\ No newline at end of file
+pub unsafe fn get_device_info(current_system: &Instance, vulkan_application_data: &VulkanApplicationData) -> DeviceInfo {
+    let gpu = vulkan_application_data.physical_device;
+    let (gpu_properties, driver) = if vulkan_application_data.driver_properties_enabled {
+        let mut driver_properties = vk::PhysicalDeviceDriverProperties::default();
+        let mut properties = vk::PhysicalDeviceProperties2::builder().push_next(&mut driver_properties);
+        current_system.get_physical_device_properties2_khr(gpu, &mut properties);
+        let gpu_properties = properties.properties;
+        let driver = DriverInfo {id: driver_properties.driver_id, name: driver_properties.driver_name.to_string(), info: driver_properties.driver_info.to_string(),
+            conformance_version: driver_properties.conformance_version};
+        (gpu_properties, Some(driver))
+    } else {
+        (get_gpu_properties(current_system, gpu), None)
+    };
+    DeviceInfo {name: gpu_properties.device_name.to_string(), device_type: gpu_properties.device_type, api_version: Version::from(gpu_properties.api_version),
+        driver_version: gpu_properties.driver_version, driver}
+}
+pub unsafe fn negotiated_api_version(current_system: &Instance, vulkan_application_data: &VulkanApplicationData) -> Version { //The core version the app can actually use, the lower of the requested one and what the chosen GPU supports.
+    let device_api_version = Version::from(get_gpu_properties(current_system, vulkan_application_data.physical_device).api_version);
+    vulkan_application_data.config.api_version.min(device_api_version)
+}
@@ -0,0 +1,73 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use vulkanalia::{Instance, vk};
+use vulkanalia::vk::{HasBuilder, KhrGetPhysicalDeviceProperties2Extension};
+use crate::graphical_core::vulkan_object::VulkanApplicationData;
+
+static TRACKED_ALLOCATED_BYTES: AtomicU64 = AtomicU64::new(0);
+
+#[derive(Copy, Clone, Debug)]
+pub struct MemoryBudgetReport {
+    pub allocated_bytes: u64, //Device-local memory in use, summed over all device-local heaps. Without VK_EXT_memory_budget only what we allocated ourselves.
+    pub budget_bytes: Option<u64> //How much device-local memory the driver is willing to give us before things start to fail or slow down, only known with VK_EXT_memory_budget.
+}
+
+pub fn track_allocation(size: vk::DeviceSize) {
+    TRACKED_ALLOCATED_BYTES.fetch_add(size, Ordering::Relaxed);
+}
+pub fn track_free(size: vk::DeviceSize) {
+    TRACKED_ALLOCATED_BYTES.fetch_sub(size, Ordering::Relaxed);
+}
+pub fn tracked_allocated_bytes() -> u64 { //Everything allocated through create_buffer and not freed yet, a lower bound on the VRAM in use that works without VK_EXT_memory_budget.
+    TRACKED_ALLOCATED_BYTES.load(Ordering::Relaxed)
+}
+pub fn find_memory_type(memory_properties: &vk::PhysicalDeviceMemoryProperties, type_filter: u32, desired_properties: vk::MemoryPropertyFlags) -> Option<u32> { //type_filter is the memory_type_bits of a resource's memory requirements.
+    (0..memory_properties.memory_type_count).find(|i| type_filter & (1 << i) != 0 && memory_properties.memory_types[*i as usize].property_flags.contains(desired_properties))
+}
+pub unsafe fn get_memory_budget(instance: &Instance, data: &VulkanApplicationData) -> MemoryBudgetReport { //Falls back to summing our own allocations without VK_EXT_memory_budget.
+    if !data.memory_budget_enabled {
+        return MemoryBudgetReport {allocated_bytes: tracked_allocated_bytes(), budget_bytes: None};
+    }
+    let mut budget_properties = vk::PhysicalDeviceMemoryBudgetPropertiesEXT::default();
+    let mut memory_properties = vk::PhysicalDeviceMemoryProperties2::builder().push_next(&mut budget_properties);
+    instance.get_physical_device_memory_properties2_khr(data.physical_device, &mut memory_properties);
+    let memory_properties = memory_properties.memory_properties;
+    let heaps = device_local_heaps(&memory_properties);
+
+    MemoryBudgetReport {
+        allocated_bytes: heaps.iter().map(|i| budget_properties.heap_usage[*i]).sum(),
+        budget_bytes: Some(heaps.iter().map(|i| budget_properties.heap_budget[*i]).sum())
+    }
+}
+fn device_local_heaps(memory_properties: &vk::PhysicalDeviceMemoryProperties) -> Vec<usize> {
+    (0..memory_properties.memory_heap_count as usize).filter(|i| memory_properties.memory_heaps[*i].flags.contains(vk::MemoryHeapFlags::DEVICE_LOCAL)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn memory_properties(types: &[vk::MemoryPropertyFlags]) -> vk::PhysicalDeviceMemoryProperties {
+        let mut memory_properties = vk::PhysicalDeviceMemoryProperties {memory_type_count: types.len() as u32, ..Default::default()};
+        for (i, flags) in types.iter().enumerate() {
+            memory_properties.memory_types[i] = vk::MemoryType {property_flags: *flags, heap_index: 0};
+        }
+        memory_properties
+    }
+    const HOST: vk::MemoryPropertyFlags = vk::MemoryPropertyFlags::from_bits_truncate(vk::MemoryPropertyFlags::HOST_VISIBLE.bits() | vk::MemoryPropertyFlags::HOST_COHERENT.bits());
+
+    #[test]
+    fn find_memory_type_skips_types_excluded_by_the_filter() {
+        let properties = memory_properties(&[HOST, HOST]);
+        assert_eq!(find_memory_type(&properties, 0b10, HOST), Some(1));
+    }
+    #[test]
+    fn find_memory_type_needs_every_requested_flag() {
+        let properties = memory_properties(&[vk::MemoryPropertyFlags::HOST_VISIBLE, vk::MemoryPropertyFlags::DEVICE_LOCAL]);
+        assert_eq!(find_memory_type(&properties, u32::MAX, HOST), None);
+    }
+    #[test]
+    fn find_memory_type_finds_the_last_type() {
+        let properties = memory_properties(&[vk::MemoryPropertyFlags::DEVICE_LOCAL, vk::MemoryPropertyFlags::DEVICE_LOCAL, HOST]);
+        assert_eq!(find_memory_type(&properties, u32::MAX, HOST), Some(2));
+    }
+}
@@ -0,0 +1,91 @@
+use std::ffi::CStr;
+use vulkanalia::{Device, Instance, vk};
+use crate::graphical_core::{
+    debug_label::DebugLabel,
+    sync::{color_subresource_range, Access, PipelineBarrier}
+};
+
+const PASS_LABEL_COLOR: [f32; 4] = [0.2, 0.6, 1.0, 1.0];
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PassOutput {
+    Swapchain, //The acquired swapchain image, its render pass takes care of the layouts.
+    Image(vk::Image) //A single mip offscreen color image, which the pass's render pass leaves in COLOR_ATTACHMENT_OPTIMAL.
+}
+
+pub struct Pass<'a> {
+    name: &'static CStr, //Shown as a debug label around the pass in GPU captures.
+    input: Option<vk::Image>, //Sampled by the fragment shader. If an earlier pass wrote it, it's transitioned for reading first.
+    output: PassOutput,
+    record: Box<dyn Fn(vk::CommandBuffer) + 'a>
+}
+impl<'a> Pass<'a> {
+    pub fn new(name: &'static CStr, input: Option<vk::Image>, output: PassOutput, record: impl Fn(vk::CommandBuffer) + 'a) -> Self {
+        Self {name, input, output, record: Box::new(record)}
+    }
+}
+
+// Not a render graph, the passes run in the order they were added. The only thing it takes care of are the barriers between a pass writing an image and a later pass sampling it.
+#[derive(Default)]
+pub struct PassList<'a> {
+    passes: Vec<Pass<'a>>
+}
+impl<'a> PassList<'a> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn add(mut self, pass: Pass<'a>) -> Self {
+        self.passes.push(pass);
+        self
+    }
+    pub unsafe fn execute(&self, instance: &Instance, device: &Device, command_buffer: vk::CommandBuffer) {
+        for (pass, transition) in self.passes.iter().zip(input_transitions(&self.passes)) {
+            if let Some(image) = transition {
+                PipelineBarrier::new().image_barrier(image, color_subresource_range(1), vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL, vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                    Access::COLOR_ATTACHMENT_WRITE, Access::FRAGMENT_SHADER_READ).execute(device, command_buffer);
+            }
+            let _label = DebugLabel::begin(instance, command_buffer, pass.name, PASS_LABEL_COLOR);
+            (pass.record)(command_buffer);
+        }
+    }
+}
+fn input_transitions(passes: &[Pass]) -> Vec<Option<vk::Image>> { //Per pass, the image that has to go from attachment to shader read layout right before it.
+    let mut written = Vec::new(); //Outputs still in COLOR_ATTACHMENT_OPTIMAL.
+    passes.iter().map(|pass| {
+        let transition = pass.input.filter(|input| written.contains(input)); //Inputs no pass wrote, like loaded textures, are already readable.
+        written.retain(|image| Some(*image) != transition);
+        if let PassOutput::Image(image) = pass.output {
+            if !written.contains(&image) {
+                written.push(image);
+            }
+        }
+        transition
+    }).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use vulkanalia::vk::Handle;
+    use super::*;
+
+    fn pass<'a>(input: Option<vk::Image>, output: PassOutput) -> Pass<'a> {
+        Pass::new(c"Test Pass", input, output, |_| ())
+    }
+
+    #[test]
+    fn a_single_swapchain_pass_needs_no_transitions() {
+        assert_eq!(input_transitions(&[pass(None, PassOutput::Swapchain)]), vec![None]);
+    }
+    #[test]
+    fn reading_an_earlier_output_transitions_it_once() {
+        let (shadow, texture) = (vk::Image::from_raw(1), vk::Image::from_raw(2));
+        let passes = [pass(None, PassOutput::Image(shadow)), pass(Some(shadow), PassOutput::Swapchain), pass(Some(shadow), PassOutput::Swapchain), pass(Some(texture), PassOutput::Swapchain)];
+        assert_eq!(input_transitions(&passes), vec![None, Some(shadow), None, None]);
+    }
+    #[test]
+    fn writing_an_image_again_needs_another_transition() {
+        let image = vk::Image::from_raw(1);
+        let passes = [pass(None, PassOutput::Image(image)), pass(Some(image), PassOutput::Swapchain), pass(None, PassOutput::Image(image)), pass(Some(image), PassOutput::Swapchain)];
+        assert_eq!(input_transitions(&passes), vec![None, Some(image), None, Some(image)]);
+    }
+}
@@ -1,43 +1,131 @@
+use std::mem::size_of;
+use anyhow::{anyhow, Context};
+use log::warn;
 use vulkanalia::{Device, vk};
 use vulkanalia::vk::{DeviceV1_0, Handle, HasBuilder};
 use crate::graphical_core::shaders::create_shader_module;
 use crate::graphical_core::vulkan_object::VulkanApplicationData;
 
+#[derive(Copy, Clone, Debug, Default)]
+pub struct DepthBias { //Pushes rasterized depth values away from the camera, used against shadow acne and z-fighting of coplanar decals.
+    pub constant_factor: f32,
+    pub slope_factor: f32
+}
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum MaterialType {
+    #[default]
+    Opaque,
+    Cutout //Alpha-tested geometry like grass and leaves, uses alpha-to-coverage. Edges are only antialiased with MSAA, at one sample per pixel it acts as a plain alpha test.
+}
+#[derive(Clone, Debug, Default)]
+pub struct SpecializationConstants { //Values for `layout(constant_id = ...) const` declarations, applied to every shader stage. IDs a shader doesn't declare are ignored.
+    entries: Vec<vk::SpecializationMapEntry>,
+    data: Vec<u8>
+}
+impl SpecializationConstants {
+    pub fn set_u32(&mut self, constant_id: u32, value: u32) {
+        self.set_bytes(constant_id, &value.to_ne_bytes());
+    }
+    pub fn set_f32(&mut self, constant_id: u32, value: f32) {
+        self.set_bytes(constant_id, &value.to_ne_bytes());
+    }
+    pub fn set_bool(&mut self, constant_id: u32, value: bool) { //GLSL bools are 32 bit VkBool32s.
+        self.set_u32(constant_id, value as u32);
+    }
+    fn set_bytes(&mut self, constant_id: u32, bytes: &[u8]) {
+        if let Some(entry) = self.entries.iter().find(|e| e.constant_id == constant_id && e.size == bytes.len()) {
+            let offset = entry.offset as usize;
+            self.data[offset..offset + bytes.len()].copy_from_slice(bytes);
+        } else {
+            self.entries.retain(|e| e.constant_id != constant_id); //A value of a different size leaves its old bytes unused in data, which is harmless.
+            self.entries.push(vk::SpecializationMapEntry::builder().constant_id(constant_id).offset(self.data.len() as u32).size(bytes.len()).build());
+            self.data.extend_from_slice(bytes);
+        }
+    }
+}
+
 pub unsafe fn create_pipeline(device: &Device, data: &mut VulkanApplicationData) -> anyhow::Result<()> {
     let vertex_shader = include_bytes!("../shaders/vert.spv");
     let fragment_shader = include_bytes!("../shaders/frag.spv");
 
-    let vertex_shader_module = create_shader_module(device, &vertex_shader[..])?;
-    let fragment_shader_module = create_shader_module(device, &fragment_shader[..])?;
+    let vertex_shader_module = create_shader_module(device, &vertex_shader[..]).context("creating vertex shader module")?;
+    let fragment_shader_module = create_shader_module(device, &fragment_shader[..]).context("creating fragment shader module")?;
 
-    let vertex_stage = vk::PipelineShaderStageCreateInfo::builder().stage(vk::ShaderStageFlags::VERTEX).module(vertex_shader_module).name(b"main\0");
-    let fragment_stage = vk::PipelineShaderStageCreateInfo::builder().stage(vk::ShaderStageFlags::FRAGMENT).module(fragment_shader_module).name(b"main\0");
-    let vertex_input_state = vk::PipelineVertexInputStateCreateInfo::builder();
+    let specialization_info = vk::SpecializationInfo::builder().map_entries(&data.render_settings.specialization_constants.entries).data(&data.render_settings.specialization_constants.data);
+    let vertex_stage = vk::PipelineShaderStageCreateInfo::builder().stage(vk::ShaderStageFlags::VERTEX).module(vertex_shader_module).name(b"main\0").specialization_info(&specialization_info);
+    let fragment_stage = vk::PipelineShaderStageCreateInfo::builder().stage(vk::ShaderStageFlags::FRAGMENT).module(fragment_shader_module).name(b"main\0").specialization_info(&specialization_info);
+    let binding_descriptions = &[data.render_settings.vertex_layout.binding_description()];
+    let attribute_descriptions = data.render_settings.vertex_layout.attribute_descriptions();
+    let vertex_input_state = vk::PipelineVertexInputStateCreateInfo::builder().vertex_binding_descriptions(binding_descriptions).vertex_attribute_descriptions(&attribute_descriptions);
     let input_assembly_state = vk::PipelineInputAssemblyStateCreateInfo::builder().topology(vk::PrimitiveTopology::TRIANGLE_LIST).primitive_restart_enable(false);
-    let viewport = vk::Viewport::builder().x(0.0).y(0.0).width(data.swapchain_accepted_images_width_and_height.width as f32).height(data.swapchain_accepted_images_width_and_height.height as f32).min_depth(0.0).max_depth(1.0);
-    let scissor = vk::Rect2D::builder().offset(vk::Offset2D { x: 0, y: 0 }).extent(data.swapchain_accepted_images_width_and_height);
-    let viewports = &[viewport];
-    let scissors = &[scissor];
-    let viewport_state = vk::PipelineViewportStateCreateInfo::builder().viewports(viewports).scissors(scissors);
+    let viewport_state = vk::PipelineViewportStateCreateInfo::builder().viewport_count(1).scissor_count(1); //Both are set while recording, so the pipeline doesn't depend on the swapchain extent.
+    let dynamic_states = &[vk::DynamicState::VIEWPORT, vk::DynamicState::SCISSOR];
+    let dynamic_state = vk::PipelineDynamicStateCreateInfo::builder().dynamic_states(dynamic_states);
+    let cull_mode = if data.render_settings.debug_winding {vk::CullModeFlags::NONE} else {vk::CullModeFlags::BACK}; //Back faces have to be rasterized for the winding debug colors to show up.
+    let depth_bias = data.render_settings.depth_bias.unwrap_or_default();
     let rasterization_state = vk::PipelineRasterizationStateCreateInfo::builder().depth_clamp_enable(false).rasterizer_discard_enable(false).polygon_mode(vk::PolygonMode::FILL)
-        .line_width(1.0).cull_mode(vk::CullModeFlags::BACK).front_face(vk::FrontFace::CLOCKWISE).depth_bias_enable(false);
-    let multisample_state = vk::PipelineMultisampleStateCreateInfo::builder().sample_shading_enable(false).rasterization_samples(vk::SampleCountFlags::_1);
-    let attachment = vk::PipelineColorBlendAttachmentState::builder().color_write_mask(vk::ColorComponentFlags::all()).blend_enable(true).src_color_blend_factor(vk::BlendFactor::SRC_ALPHA)
+        .line_width(1.0).cull_mode(cull_mode).front_face(vk::FrontFace::COUNTER_CLOCKWISE) //The projection flips Y, so the usual counter-clockwise front faces stay counter-clockwise on screen.
+        .depth_bias_enable(data.render_settings.depth_bias.is_some()).depth_bias_constant_factor(depth_bias.constant_factor).depth_bias_slope_factor(depth_bias.slope_factor).depth_bias_clamp(0.0);
+    let min_sample_shading = data.render_settings.min_sample_shading.filter(|_| data.sample_rate_shading_enabled); //A recreated device might lack the feature.
+    let multisample_state = vk::PipelineMultisampleStateCreateInfo::builder().sample_shading_enable(min_sample_shading.is_some())
+        .min_sample_shading(min_sample_shading.unwrap_or_default()).rasterization_samples(vk::SampleCountFlags::_1)
+        .alpha_to_coverage_enable(data.render_settings.material_type == MaterialType::Cutout);
+    // Opaque geometry must not blend: overlapping triangles drawn out of order would otherwise mix with whatever was drawn before them.
+    let attachment = vk::PipelineColorBlendAttachmentState::builder().color_write_mask(vk::ColorComponentFlags::all()).blend_enable(data.render_settings.blending_enabled).src_color_blend_factor(vk::BlendFactor::SRC_ALPHA)
         .dst_color_blend_factor(vk::BlendFactor::ONE_MINUS_SRC_ALPHA).color_blend_op(vk::BlendOp::ADD).src_alpha_blend_factor(vk::BlendFactor::ONE).dst_alpha_blend_factor(vk::BlendFactor::ZERO).alpha_blend_op(vk::BlendOp::ADD);
     let attachments = &[attachment];
     let color_blend_state = vk::PipelineColorBlendStateCreateInfo::builder().logic_op_enable(false).logic_op(vk::LogicOp::COPY).attachments(attachments).blend_constants([0.0, 0.0, 0.0, 0.0]);
-    let layout_info = vk::PipelineLayoutCreateInfo::builder();
+    let push_constant_range = vk::PushConstantRange::builder().stage_flags(vk::ShaderStageFlags::FRAGMENT).offset(0).size(size_of::<u32>() as u32);
+    let push_constant_ranges = &[push_constant_range];
+    let set_layouts = &[data.descriptor_set_layout];
+    let layout_info = vk::PipelineLayoutCreateInfo::builder().set_layouts(set_layouts).push_constant_ranges(push_constant_ranges);
 
-    data.pipeline_layout = device.create_pipeline_layout(&layout_info, None)?;
+    data.pipeline_layout = device.create_pipeline_layout(&layout_info, None).context("creating pipeline layout")?;
 
     let stages = &[vertex_stage, fragment_stage];
     let info = vk::GraphicsPipelineCreateInfo::builder().stages(stages).vertex_input_state(&vertex_input_state).input_assembly_state(&input_assembly_state).viewport_state(&viewport_state)
-        .rasterization_state(&rasterization_state).multisample_state(&multisample_state).color_blend_state(&color_blend_state).layout(data.pipeline_layout).render_pass(data.render_pass).subpass(0);
+        .rasterization_state(&rasterization_state).multisample_state(&multisample_state).color_blend_state(&color_blend_state).dynamic_state(&dynamic_state).layout(data.pipeline_layout).render_pass(data.render_pass).subpass(0);
 
-    data.pipeline = device.create_graphics_pipelines(vk::PipelineCache::null(), &[info], None)?.0[0];
+    let (pipelines, result) = device.create_graphics_pipelines(vk::PipelineCache::null(), &[info], None).context("creating graphics pipeline")?;
+    if result != vk::SuccessCode::SUCCESS {
+        warn!("Creating the graphics pipeline returned {:?}.", result);
+    }
+    data.pipeline = pipelines.first().copied().filter(|pipeline| !pipeline.is_null())
+        .ok_or_else(|| anyhow!("Creating the graphics pipeline returned {:?} but no pipeline was created.", result))?;
 
 
     device.destroy_shader_module(vertex_shader_module, None);
     device.destroy_shader_module(fragment_shader_module, None);
     Ok(())
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn overwriting_a_constant_of_the_same_size_updates_it_in_place() {
+        let mut constants = SpecializationConstants::default();
+        constants.set_u32(3, 1);
+        constants.set_u32(3, 7);
+        assert_eq!(constants.entries.len(), 1);
+        assert_eq!(constants.data, 7u32.to_ne_bytes());
+    }
+    #[test]
+    fn resetting_a_constant_with_another_size_leaves_one_entry() {
+        let mut constants = SpecializationConstants::default();
+        constants.set_bytes(3, &[1, 2]);
+        constants.set_u32(3, 9);
+        assert_eq!(constants.entries.len(), 1);
+        let entry = constants.entries[0];
+        assert_eq!((entry.constant_id, entry.size), (3, 4));
+        assert_eq!(constants.data[entry.offset as usize..entry.offset as usize + entry.size], 9u32.to_ne_bytes());
+    }
+    #[test]
+    fn bools_are_four_bytes() {
+        let mut constants = SpecializationConstants::default();
+        constants.set_bool(0, true);
+        assert_eq!(constants.entries[0].size, 4);
+        assert_eq!(constants.data, 1u32.to_ne_bytes());
+    }
+}
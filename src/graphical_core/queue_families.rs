@@ -36,10 +36,5 @@ unsafe fn queue_family_has_capability_of_presenting_to_our_window_surface(curren
     current_system.get_physical_device_surface_support_khr(gpu, index as u32, vulkan_application_data.surface)
 }
 fn queue_family_indexes_not_empty(graphics_queue_index: Option<u32>, presentation_queue_index: Option<u32>) -> bool{
-    if let (Some(graphics_queue_index), Some(presentation_queue_index)) = (graphics_queue_index, presentation_queue_index) {
-        true
-    }
-    else {
-        false
-    }
+    graphics_queue_index.is_some() && presentation_queue_index.is_some()
 }
@@ -1,24 +1,50 @@
+use anyhow::Context;
 use vulkanalia::{Device, Instance, vk};
 use vulkanalia::vk::{DeviceV1_0, HasBuilder};
 use crate::graphical_core::vulkan_object::VulkanApplicationData;
 
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum ColorLoadOp {
+    #[default]
+    Clear,
+    DontCare //Skips the clear, only for frames where every pixel gets drawn over anyway.
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ClearColor(pub [f32; 4]); //RGBA. Alpha only matters for targets that get blended later, the compositor ignores it for the OPAQUE swapchain.
+impl Default for ClearColor {
+    fn default() -> Self {
+        Self([0.0, 0.0, 0.0, 1.0]) //Black screen that replaces the screen between each shown frame.
+    }
+}
+
+const COLOR_ATTACHMENT_INDEX: u32 = 0;
+pub const ACQUIRE_WAIT_STAGE: vk::PipelineStageFlags = vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT; //Where rendering waits for the swapchain image, shared by the submit and the render pass's external dependency.
+
+pub fn render_pass_clear_values(data: &VulkanApplicationData) -> Vec<vk::ClearValue> { //One entry per render pass attachment, in attachment order. Add depth/resolve entries here together with their attachments.
+    let color_clear_value = vk::ClearValue {color: vk::ClearColorValue {float32: data.render_settings.clear_color.0}};
+    vec![color_clear_value] //At COLOR_ATTACHMENT_INDEX.
+}
+
 pub unsafe fn create_render_pass(instance: &Instance, device: &Device, data: &mut VulkanApplicationData) -> anyhow::Result<()> {
-    let color_attachment = vk::AttachmentDescription::builder().format(data.swapchain_format).samples(vk::SampleCountFlags::_1).load_op(vk::AttachmentLoadOp::CLEAR).store_op(vk::AttachmentStoreOp::STORE)
+    let load_op = match data.render_settings.color_load_op {
+        ColorLoadOp::Clear => vk::AttachmentLoadOp::CLEAR,
+        ColorLoadOp::DontCare => vk::AttachmentLoadOp::DONT_CARE
+    };
+    //The render pass itself leaves the image in PRESENT_SRC_KHR, no separate layout transition is needed before presenting.
+    let color_attachment = vk::AttachmentDescription::builder().format(data.swapchain_format).samples(vk::SampleCountFlags::_1).load_op(load_op).store_op(vk::AttachmentStoreOp::STORE)
         .stencil_load_op(vk::AttachmentLoadOp::DONT_CARE).stencil_store_op(vk::AttachmentStoreOp::DONT_CARE).initial_layout(vk::ImageLayout::UNDEFINED).final_layout(vk::ImageLayout::PRESENT_SRC_KHR);
-    let color_attachment_ref = vk::AttachmentReference::builder().attachment(0).layout(vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL);
+    let color_attachment_ref = vk::AttachmentReference::builder().attachment(COLOR_ATTACHMENT_INDEX).layout(vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL);
     let color_attachments = &[color_attachment_ref];
     let subpass = vk::SubpassDescription::builder().pipeline_bind_point(vk::PipelineBindPoint::GRAPHICS).color_attachments(color_attachments);
-    let attachments = &[color_attachment];
-    let subpasses = &[subpass];
-    let info = vk::RenderPassCreateInfo::builder().attachments(attachments).subpasses(subpasses);
-
-    data.render_pass = device.create_render_pass(&info, None)?;
+    //Delays the color writes until the image-available semaphore was signaled. The submit in render_frame waits on that semaphore at the same stage,
+    //both use ACQUIRE_WAIT_STAGE so they can't drift apart. Without this dependency the implicit external one lets the layout transition run before the image is acquired.
     let dependency = vk::SubpassDependency::builder()
         .src_subpass(vk::SUBPASS_EXTERNAL)
         .dst_subpass(0)
-        .src_stage_mask(vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT)
+        .src_stage_mask(ACQUIRE_WAIT_STAGE)
         .src_access_mask(vk::AccessFlags::empty())
-        .dst_stage_mask(vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT)
+        .dst_stage_mask(ACQUIRE_WAIT_STAGE)
         .dst_access_mask(vk::AccessFlags::COLOR_ATTACHMENT_WRITE);
     let attachments = &[color_attachment];
     let subpasses = &[subpass];
@@ -27,5 +53,7 @@ pub unsafe fn create_render_pass(instance: &Instance, device: &Device, data: &mu
         .attachments(attachments)
         .subpasses(subpasses)
         .dependencies(dependencies);
+
+    data.render_pass = device.create_render_pass(&info, None).context("creating render pass")?;
     Ok(())
-}
\ No newline at end of file
+}
@@ -1,5 +1,8 @@
+use anyhow::{anyhow, Context};
 use vulkanalia::{Device, Instance, vk};
 use vulkanalia::vk::{DeviceV1_0, Handle, HasBuilder, KhrSurfaceExtension, KhrSwapchainExtension};
+use log::{info, warn};
+use thiserror::Error;
 use winit::window::Window;
 use crate::graphical_core::queue_families::RequiredQueueFamilies;
 use crate::graphical_core::vulkan_object::VulkanApplicationData;
@@ -9,17 +12,32 @@ pub unsafe fn create_swapchain(user_window: &Window, current_system: &Instance,
     let current_swapchain_capabilities = SwapchainSupport::get(current_system, vulkan_application_data, vulkan_application_data.physical_device)?;
 
     let surface_format = get_swapchain_surface_format(&current_swapchain_capabilities.formats);
-    let presentation_mode = get_swapchain_presentation_mode(&current_swapchain_capabilities.present_modes);
-    let swapchain_image_resolution = get_swapchain_extent(user_window, current_swapchain_capabilities.capabilities);
+    let (presentation_mode, image_count) = get_swapchain_presentation_mode_and_image_count(&current_swapchain_capabilities.present_modes, current_swapchain_capabilities.capabilities,
+        vulkan_application_data.render_settings.requested_present_mode);
+    let swapchain_image_resolution = get_swapchain_extent(user_window, current_swapchain_capabilities.capabilities)?;
+    let composite_alpha = get_swapchain_composite_alpha(current_swapchain_capabilities.capabilities.supported_composite_alpha);
+    info!("Using swapchain composite alpha mode {:?}.", composite_alpha);
 
     vulkan_application_data.swapchain_format = surface_format.format;
     vulkan_application_data.swapchain_accepted_images_width_and_height = swapchain_image_resolution;
 
-    let mut image_count = current_swapchain_capabilities.capabilities.min_image_count + 1; //We add one more image to the image count to make sure we never have to wait for an image.
-    if current_swapchain_capabilities.capabilities.max_image_count != 0 && image_count > current_swapchain_capabilities.capabilities.max_image_count {
-        image_count = current_swapchain_capabilities.capabilities.max_image_count;
+    let image_array_layers = vulkan_application_data.config.swapchain_image_array_layers;
+    if image_array_layers == 0 || image_array_layers > current_swapchain_capabilities.capabilities.max_image_array_layers {
+        return Err(anyhow!("Requested {} swapchain image array layers, the surface supports 1 to {}.", image_array_layers, current_swapchain_capabilities.capabilities.max_image_array_layers));
     }
+    if image_array_layers > 1 {
+        return Err(anyhow!("Requested {} swapchain image array layers, but rendering to more than one layer requires multiview support, which isn't implemented.", image_array_layers));
+    }
+
+    let image_usage = vulkan_application_data.config.swapchain_image_usage | vk::ImageUsageFlags::COLOR_ATTACHMENT;
+    let unsupported_usage = image_usage & !current_swapchain_capabilities.capabilities.supported_usage_flags;
+    if !unsupported_usage.is_empty() {
+        return Err(anyhow!("Swapchain image usage {:?} isn't supported by the surface, it supports {:?}.", unsupported_usage, current_swapchain_capabilities.capabilities.supported_usage_flags));
+    }
+    info!("Using swapchain image usage {:?}.", image_usage);
 
+    //With separate graphics and presentation families the images are shared CONCURRENTLY, so presenting them needs no queue family ownership transfer.
+    //EXCLUSIVE sharing would require a release barrier on the graphics queue and an acquire barrier on the presentation queue every frame.
     let mut queue_family_indices = vec![];
     let image_sharing_mode = if indices.graphics_queue_index != indices.presentation_queue_index {
         queue_family_indices.push(indices.graphics_queue_index);
@@ -35,18 +53,18 @@ pub unsafe fn create_swapchain(user_window: &Window, current_system: &Instance,
         .image_format(surface_format.format)
         .image_color_space(surface_format.color_space)
         .image_extent(swapchain_image_resolution)
-        .image_array_layers(1)
-        .image_usage(vk::ImageUsageFlags::COLOR_ATTACHMENT)
+        .image_array_layers(image_array_layers)
+        .image_usage(image_usage)
         .image_sharing_mode(image_sharing_mode)
         .queue_family_indices(&queue_family_indices)
         .pre_transform(current_swapchain_capabilities.capabilities.current_transform)
-        .composite_alpha(vk::CompositeAlphaFlagsKHR::OPAQUE)
+        .composite_alpha(composite_alpha)
         .present_mode(presentation_mode)
         .clipped(true)
         .old_swapchain(vk::SwapchainKHR::null());
 
-    vulkan_application_data.swapchain = vulkan_logical_device.create_swapchain_khr(&info, None)?;
-    vulkan_application_data.swapchain_images = vulkan_logical_device.get_swapchain_images_khr(vulkan_application_data.swapchain)?;
+    vulkan_application_data.swapchain = vulkan_logical_device.create_swapchain_khr(&info, None).context("creating swapchain")?;
+    vulkan_application_data.swapchain_images = vulkan_logical_device.get_swapchain_images_khr(vulkan_application_data.swapchain).context("getting swapchain images")?;
 
     Ok(())
 }
@@ -55,20 +73,66 @@ fn get_swapchain_surface_format(formats: &[vk::SurfaceFormatKHR]) -> vk::Surface
     formats.iter().cloned().find(|f| f.format == vk::Format::B8G8R8A8_SRGB && f.color_space == vk::ColorSpaceKHR::SRGB_NONLINEAR).unwrap_or_else(|| formats[0])
 }
 
-fn get_swapchain_presentation_mode(present_modes: &[vk::PresentModeKHR]) -> vk::PresentModeKHR {
-    present_modes.iter().cloned().find(|m| *m == vk::PresentModeKHR::MAILBOX).unwrap_or(vk::PresentModeKHR::FIFO)
+const MAILBOX_MIN_IMAGE_COUNT: u32 = 3; //One image being presented, one queued and one to render to, with fewer MAILBOX blocks just like FIFO.
+
+fn get_swapchain_presentation_mode_and_image_count(present_modes: &[vk::PresentModeKHR], capabilities: vk::SurfaceCapabilitiesKHR, requested_present_mode: Option<vk::PresentModeKHR>)
+    -> (vk::PresentModeKHR, u32) { //Without a requested mode MAILBOX is preferred, any mode that can't be used falls back to FIFO.
+    let clamp_image_count = |count: u32| if capabilities.max_image_count != 0 {count.min(capabilities.max_image_count)} else {count};
+    let image_count = clamp_image_count(capabilities.min_image_count + 1); //We add one more image to the image count to make sure we never have to wait for an image.
+    match requested_present_mode {
+        Some(vk::PresentModeKHR::MAILBOX) | None => (),
+        Some(requested) if present_modes.contains(&requested) => return (requested, image_count),
+        Some(requested) => {
+            warn!("Present mode {:?} isn't supported by the surface, falling back to FIFO.", requested); //IMMEDIATE in particular is missing on many compositors.
+            return (vk::PresentModeKHR::FIFO, image_count);
+        }
+    }
+    if present_modes.contains(&vk::PresentModeKHR::MAILBOX) {
+        let mailbox_image_count = clamp_image_count(image_count.max(MAILBOX_MIN_IMAGE_COUNT));
+        if mailbox_image_count >= MAILBOX_MIN_IMAGE_COUNT {
+            return (vk::PresentModeKHR::MAILBOX, mailbox_image_count);
+        }
+        warn!("MAILBOX is supported but the surface allows at most {} images, it needs {}. Falling back to FIFO.", capabilities.max_image_count, MAILBOX_MIN_IMAGE_COUNT);
+    } else if let Some(requested) = requested_present_mode { //Only MAILBOX gets here, without a request the fallback is expected and not worth a warning.
+        warn!("Present mode {:?} was requested but isn't supported by the surface, falling back to {:?}.", requested, vk::PresentModeKHR::FIFO);
+    }
+    (vk::PresentModeKHR::FIFO, image_count) //FIFO is always supported.
 }
 
-fn get_swapchain_extent(window: &Window, capabilities: vk::SurfaceCapabilitiesKHR) -> vk::Extent2D {
-    if capabilities.current_extent.width != u32::MAX {
-        capabilities.current_extent
+fn get_swapchain_composite_alpha(supported: vk::CompositeAlphaFlagsKHR) -> vk::CompositeAlphaFlagsKHR { //Some compositors don't report OPAQUE as supported.
+    [vk::CompositeAlphaFlagsKHR::OPAQUE, vk::CompositeAlphaFlagsKHR::INHERIT, vk::CompositeAlphaFlagsKHR::PRE_MULTIPLIED, vk::CompositeAlphaFlagsKHR::POST_MULTIPLIED]
+        .into_iter().find(|mode| supported.contains(*mode)).unwrap_or(vk::CompositeAlphaFlagsKHR::OPAQUE)
+}
+
+#[derive(Debug, Error)]
+#[error("The swapchain extent is {0}x{1}, a swapchain can't be created until the window has a non-zero size again.")]
+pub struct ZeroSizedExtentError(pub u32, pub u32);
+
+pub fn get_swapchain_extent(window: &Window, capabilities: vk::SurfaceCapabilitiesKHR) -> anyhow::Result<vk::Extent2D> { //Fails with ZeroSizedExtentError while minimized, callers can wait and retry.
+    let size = window.inner_size(); //Physical pixels, so HiDPI windows aren't rendered at their logical size and upscaled.
+    let extent = clamp_extent((size.width, size.height), &capabilities);
+    if extent.width == 0 || extent.height == 0 {
+        return Err(anyhow!(ZeroSizedExtentError(extent.width, extent.height)));
     }
-    else {
-        let size = window.inner_size();
-        let clamp = |min: u32, max: u32, v: u32| min.max(max.min(v));
-        vk::Extent2D::builder().width(clamp(capabilities.min_image_extent.width, capabilities.max_image_extent.width, size.width))
-            .height(clamp(capabilities.min_image_extent.height, capabilities.max_image_extent.height, size.height)).build()
+    Ok(extent)
+}
+pub fn clamp_extent(desired: (u32, u32), capabilities: &vk::SurfaceCapabilitiesKHR) -> vk::Extent2D { //The surface dictates the extent unless it reports u32::MAX, then the desired size is clamped to its limits.
+    if capabilities.current_extent.width != u32::MAX {
+        return capabilities.current_extent;
     }
+    let clamp = |min: u32, max: u32, v: u32| min.max(max.min(v));
+    vk::Extent2D::builder().width(clamp(capabilities.min_image_extent.width, capabilities.max_image_extent.width, desired.0))
+        .height(clamp(capabilities.min_image_extent.height, capabilities.max_image_extent.height, desired.1)).build()
+}
+pub fn clip_to_extent(rect: vk::Rect2D, extent: vk::Extent2D) -> Option<vk::Rect2D> { //None when nothing of the rect is left inside 0..extent.
+    let clip = |offset: i32, length: u32, limit: u32| {
+        let start = (offset as i64).clamp(0, limit as i64);
+        let end = (offset as i64 + length as i64).clamp(0, limit as i64);
+        (start as i32, (end - start) as u32)
+    };
+    let (x, width) = clip(rect.offset.x, rect.extent.width, extent.width);
+    let (y, height) = clip(rect.offset.y, rect.extent.height, extent.height);
+    (width > 0 && height > 0).then_some(vk::Rect2D {offset: vk::Offset2D {x, y}, extent: vk::Extent2D {width, height}})
 }
 pub unsafe fn create_swapchain_image_views(device: &Device, data: &mut VulkanApplicationData) -> anyhow::Result<()> {
     data.swapchain_image_views = data.swapchain_images.iter().map(|i|{
@@ -79,7 +143,7 @@ pub unsafe fn create_swapchain_image_views(device: &Device, data: &mut VulkanApp
         let info = vk::ImageViewCreateInfo::builder().image(*i).view_type(vk::ImageViewType::_2D).format(data.swapchain_format).components(components)
             .subresource_range(subresource_range);
         device.create_image_view(&info, None)
-    }).collect::<anyhow::Result<Vec<_>, _>>()?;
+    }).collect::<anyhow::Result<Vec<_>, _>>().context("creating swapchain image views")?;
     Ok(())
 }
 
@@ -97,4 +161,83 @@ impl SwapchainSupport {
             present_modes: instance.get_physical_device_surface_present_modes_khr(physical_device, data.surface)?,
         })
     }
-}
\ No newline at end of file
+    pub fn is_adequate(&self) -> bool {
+        self.inadequacy_reason().is_none()
+    }
+    pub fn inadequacy_reason(&self) -> Option<&'static str> { //Worded to fit SuitabilityError's "Missing {0}." message.
+        if self.formats.is_empty() {
+            Some("swapchain surface formats")
+        } else if self.present_modes.is_empty() {
+            Some("swapchain present modes")
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn capabilities(current: (u32, u32)) -> vk::SurfaceCapabilitiesKHR {
+        vk::SurfaceCapabilitiesKHR {
+            current_extent: vk::Extent2D {width: current.0, height: current.1},
+            min_image_extent: vk::Extent2D {width: 100, height: 50},
+            max_image_extent: vk::Extent2D {width: 1920, height: 1080},
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn clamp_extent_uses_current_extent_when_the_surface_dictates_it() {
+        let extent = clamp_extent((4000, 10), &capabilities((800, 600)));
+        assert_eq!((extent.width, extent.height), (800, 600));
+    }
+    #[test]
+    fn clamp_extent_clamps_up_to_the_minimum() {
+        let extent = clamp_extent((10, 20), &capabilities((u32::MAX, u32::MAX)));
+        assert_eq!((extent.width, extent.height), (100, 50));
+    }
+    #[test]
+    fn clamp_extent_clamps_down_to_the_maximum() {
+        let extent = clamp_extent((4000, 3000), &capabilities((u32::MAX, u32::MAX)));
+        assert_eq!((extent.width, extent.height), (1920, 1080));
+    }
+
+    #[test]
+    fn unsupported_mailbox_request_falls_back_to_fifo() {
+        let capabilities = vk::SurfaceCapabilitiesKHR {min_image_count: 2, max_image_count: 0, ..Default::default()};
+        let present_modes = [vk::PresentModeKHR::FIFO, vk::PresentModeKHR::IMMEDIATE];
+        assert_eq!(get_swapchain_presentation_mode_and_image_count(&present_modes, capabilities, Some(vk::PresentModeKHR::MAILBOX)), (vk::PresentModeKHR::FIFO, 3));
+    }
+    #[test]
+    fn mailbox_needing_more_images_than_allowed_falls_back_to_fifo() {
+        let capabilities = vk::SurfaceCapabilitiesKHR {min_image_count: 2, max_image_count: 2, ..Default::default()};
+        let present_modes = [vk::PresentModeKHR::FIFO, vk::PresentModeKHR::MAILBOX];
+        assert_eq!(get_swapchain_presentation_mode_and_image_count(&present_modes, capabilities, None), (vk::PresentModeKHR::FIFO, 2));
+        assert_eq!(get_swapchain_presentation_mode_and_image_count(&present_modes, capabilities, Some(vk::PresentModeKHR::MAILBOX)), (vk::PresentModeKHR::FIFO, 2));
+    }
+    #[test]
+    fn mailbox_without_an_image_limit_gets_three_images() {
+        let capabilities = vk::SurfaceCapabilitiesKHR {min_image_count: 1, max_image_count: 0, ..Default::default()};
+        let present_modes = [vk::PresentModeKHR::FIFO, vk::PresentModeKHR::MAILBOX];
+        assert_eq!(get_swapchain_presentation_mode_and_image_count(&present_modes, capabilities, None), (vk::PresentModeKHR::MAILBOX, 3));
+    }
+
+    fn rect(x: i32, y: i32, width: u32, height: u32) -> vk::Rect2D {
+        vk::Rect2D {offset: vk::Offset2D {x, y}, extent: vk::Extent2D {width, height}}
+    }
+    #[test]
+    fn clip_to_extent_keeps_rects_inside() {
+        assert_eq!(clip_to_extent(rect(10, 20, 30, 40), vk::Extent2D {width: 100, height: 100}), Some(rect(10, 20, 30, 40)));
+    }
+    #[test]
+    fn clip_to_extent_cuts_off_what_sticks_out() {
+        assert_eq!(clip_to_extent(rect(-10, 90, 30, 40), vk::Extent2D {width: 100, height: 100}), Some(rect(0, 90, 20, 10)));
+    }
+    #[test]
+    fn clip_to_extent_drops_rects_outside() {
+        assert_eq!(clip_to_extent(rect(100, 0, 30, 40), vk::Extent2D {width: 100, height: 100}), None);
+        assert_eq!(clip_to_extent(rect(0, 0, 0, 40), vk::Extent2D {width: 100, height: 100}), None);
+    }
+}
@@ -0,0 +1,117 @@
+use vulkanalia::{Device, vk};
+use vulkanalia::vk::{DeviceV1_0, HasBuilder};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Access { //A stage together with what it does to the memory, one side of a barrier.
+    pub stage: vk::PipelineStageFlags,
+    pub access: vk::AccessFlags
+}
+impl Access {
+    pub const NONE: Self = Self {stage: vk::PipelineStageFlags::TOP_OF_PIPE, access: vk::AccessFlags::empty()}; //Nothing to wait for, e.g. before the first use of a fresh image.
+    pub const TRANSFER_WRITE: Self = Self {stage: vk::PipelineStageFlags::TRANSFER, access: vk::AccessFlags::TRANSFER_WRITE};
+    pub const TRANSFER_READ: Self = Self {stage: vk::PipelineStageFlags::TRANSFER, access: vk::AccessFlags::TRANSFER_READ};
+    pub const VERTEX_INPUT: Self = Self {stage: vk::PipelineStageFlags::VERTEX_INPUT, access: vk::AccessFlags::from_bits_truncate(vk::AccessFlags::VERTEX_ATTRIBUTE_READ.bits() | vk::AccessFlags::INDEX_READ.bits())};
+    pub const UNIFORM_READ: Self = Self {stage: vk::PipelineStageFlags::VERTEX_SHADER, access: vk::AccessFlags::UNIFORM_READ};
+    pub const FRAGMENT_SHADER_READ: Self = Self {stage: vk::PipelineStageFlags::FRAGMENT_SHADER, access: vk::AccessFlags::SHADER_READ};
+    pub const COLOR_ATTACHMENT_WRITE: Self = Self {stage: vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT, access: vk::AccessFlags::COLOR_ATTACHMENT_WRITE};
+
+    pub fn buffer_reads(usage: vk::BufferUsageFlags) -> Self { //Everything that reads a buffer created with these usage flags while drawing.
+        let mut reads = Self {stage: vk::PipelineStageFlags::empty(), access: vk::AccessFlags::empty()};
+        if usage.intersects(vk::BufferUsageFlags::VERTEX_BUFFER | vk::BufferUsageFlags::INDEX_BUFFER) {
+            reads = reads.and(Self::VERTEX_INPUT);
+        }
+        if usage.contains(vk::BufferUsageFlags::UNIFORM_BUFFER) {
+            reads = reads.and(Self::UNIFORM_READ);
+        }
+        if reads.stage.is_empty() { //A usage this doesn't know about, so wait for the whole pipeline.
+            reads = Self {stage: vk::PipelineStageFlags::ALL_COMMANDS, access: vk::AccessFlags::MEMORY_READ};
+        }
+        reads
+    }
+    fn and(self, other: Self) -> Self {
+        Self {stage: self.stage | other.stage, access: self.access | other.access}
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct PipelineBarrier { //Collects buffer and image barriers between two sets of stages and records them with a single cmd_pipeline_barrier.
+    src_stage: vk::PipelineStageFlags,
+    dst_stage: vk::PipelineStageFlags,
+    buffer_barriers: Vec<vk::BufferMemoryBarrier>,
+    image_barriers: Vec<vk::ImageMemoryBarrier>
+}
+impl PipelineBarrier {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn buffer_barrier(mut self, buffer: vk::Buffer, src: Access, dst: Access) -> Self { //Covers the whole buffer.
+        self.src_stage |= src.stage;
+        self.dst_stage |= dst.stage;
+        self.buffer_barriers.push(vk::BufferMemoryBarrier::builder().buffer(buffer).offset(0).size(vk::WHOLE_SIZE as vk::DeviceSize)
+            .src_access_mask(src.access).dst_access_mask(dst.access)
+            .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED).dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED).build());
+        self
+    }
+    pub fn image_barrier(mut self, image: vk::Image, subresource_range: vk::ImageSubresourceRange, old_layout: vk::ImageLayout, new_layout: vk::ImageLayout, src: Access, dst: Access) -> Self {
+        self.src_stage |= src.stage;
+        self.dst_stage |= dst.stage;
+        self.image_barriers.push(vk::ImageMemoryBarrier::builder().image(image).subresource_range(subresource_range)
+            .old_layout(old_layout).new_layout(new_layout).src_access_mask(src.access).dst_access_mask(dst.access)
+            .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED).dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED).build());
+        self
+    }
+    pub fn upload_buffer(self, buffer: vk::Buffer, dst: Access) -> Self { //After a copy_buffer into it, before it gets read.
+        self.buffer_barrier(buffer, Access::TRANSFER_WRITE, dst)
+    }
+    pub fn prepare_image_upload(self, image: vk::Image, subresource_range: vk::ImageSubresourceRange) -> Self { //Before copying into a fresh image, its old contents are discarded.
+        self.image_barrier(image, subresource_range, vk::ImageLayout::UNDEFINED, vk::ImageLayout::TRANSFER_DST_OPTIMAL, Access::NONE, Access::TRANSFER_WRITE)
+    }
+    pub fn finish_image_upload(self, image: vk::Image, subresource_range: vk::ImageSubresourceRange) -> Self { //After the copy, before the fragment shader samples it.
+        self.image_barrier(image, subresource_range, vk::ImageLayout::TRANSFER_DST_OPTIMAL, vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL, Access::TRANSFER_WRITE, Access::FRAGMENT_SHADER_READ)
+    }
+    pub fn is_empty(&self) -> bool {
+        self.buffer_barriers.is_empty() && self.image_barriers.is_empty()
+    }
+    pub unsafe fn execute(&self, device: &Device, command_buffer: vk::CommandBuffer) {
+        if self.is_empty() {
+            return;
+        }
+        device.cmd_pipeline_barrier(command_buffer, self.src_stage, self.dst_stage, vk::DependencyFlags::empty(), &[] as &[vk::MemoryBarrier], &self.buffer_barriers, &self.image_barriers);
+    }
+}
+
+pub fn color_subresource_range(mip_levels: u32) -> vk::ImageSubresourceRange { //All mip levels of the single layer of a color image.
+    vk::ImageSubresourceRange::builder().aspect_mask(vk::ImageAspectFlags::COLOR).base_mip_level(0).level_count(mip_levels).base_array_layer(0).layer_count(1).build()
+}
+
+#[cfg(test)]
+mod tests {
+    use vulkanalia::vk::Handle;
+    use super::*;
+
+    #[test]
+    fn buffer_reads_matches_the_usage() {
+        assert_eq!(Access::buffer_reads(vk::BufferUsageFlags::VERTEX_BUFFER), Access::VERTEX_INPUT);
+        assert_eq!(Access::buffer_reads(vk::BufferUsageFlags::INDEX_BUFFER), Access::VERTEX_INPUT);
+        assert_eq!(Access::buffer_reads(vk::BufferUsageFlags::UNIFORM_BUFFER), Access::UNIFORM_READ);
+        let both = Access::buffer_reads(vk::BufferUsageFlags::VERTEX_BUFFER | vk::BufferUsageFlags::UNIFORM_BUFFER);
+        assert_eq!(both.stage, vk::PipelineStageFlags::VERTEX_INPUT | vk::PipelineStageFlags::VERTEX_SHADER);
+        assert_eq!(both.access, vk::AccessFlags::VERTEX_ATTRIBUTE_READ | vk::AccessFlags::INDEX_READ | vk::AccessFlags::UNIFORM_READ);
+    }
+    #[test]
+    fn barriers_combine_their_stages() {
+        let barrier = PipelineBarrier::new()
+            .upload_buffer(vk::Buffer::null(), Access::VERTEX_INPUT)
+            .finish_image_upload(vk::Image::null(), color_subresource_range(1));
+        assert_eq!(barrier.src_stage, vk::PipelineStageFlags::TRANSFER);
+        assert_eq!(barrier.dst_stage, vk::PipelineStageFlags::VERTEX_INPUT | vk::PipelineStageFlags::FRAGMENT_SHADER);
+        assert_eq!((barrier.buffer_barriers.len(), barrier.image_barriers.len()), (1, 1));
+        assert!(!barrier.is_empty());
+    }
+    #[test]
+    fn empty_barrier_records_nothing() {
+        let barrier = PipelineBarrier::new();
+        assert!(barrier.is_empty());
+        assert!(barrier.src_stage.is_empty() && barrier.dst_stage.is_empty());
+    }
+}
@@ -0,0 +1,100 @@
+use vulkanalia::vk;
+use vulkanalia::vk::HasBuilder;
+
+const CHECKER_SIZE: u32 = 32;
+const GRAY_RAMP_STEPS: u32 = 16;
+const UV_GRID_COLUMNS: u32 = 16;
+const UV_GRID_ROWS: u32 = 4;
+const BLACK: [f32; 4] = [0.0, 0.0, 0.0, 1.0];
+const WHITE: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+
+// Calibration image drawn with cmd_clear_attachments instead of the scene, so it doesn't depend on any shader.
+// Top half: black/white checkerboard. Below that: a gray ramp, a stepped UV gradient (red = u, green = v),
+// and 1px black/white lines next to a 50% gray patch. The clear values are linear, so with an sRGB swapchain
+// the patch should look as bright as the lines from a distance. If it looks darker or brighter, gamma is applied twice or not at all.
+pub fn test_pattern_regions(extent: vk::Extent2D) -> Vec<([f32; 4], Vec<vk::ClearRect>)> { //Grouped by color, one cmd_clear_attachments per entry.
+    let (width, height) = (extent.width, extent.height);
+    let mut regions = vec![(BLACK, vec![rect(0, 0, width, height)]), (WHITE, Vec::new())];
+
+    let checker_height = height / 2;
+    for y in (0..checker_height).step_by(CHECKER_SIZE as usize) {
+        for x in (0..width).step_by(CHECKER_SIZE as usize) {
+            if (x / CHECKER_SIZE + y / CHECKER_SIZE).is_multiple_of(2) {
+                regions[1].1.push(rect(x, y, CHECKER_SIZE.min(width - x), CHECKER_SIZE.min(checker_height - y)));
+            }
+        }
+    }
+
+    let band_height = (height - checker_height) / 3;
+    let ramp_top = checker_height;
+    for step in 0..GRAY_RAMP_STEPS {
+        let gray = step as f32 / (GRAY_RAMP_STEPS - 1) as f32;
+        let (x, step_width) = split(width, GRAY_RAMP_STEPS, step);
+        regions.push(([gray, gray, gray, 1.0], vec![rect(x, ramp_top, step_width, band_height)]));
+    }
+
+    let uv_top = ramp_top + band_height;
+    for row in 0..UV_GRID_ROWS {
+        for column in 0..UV_GRID_COLUMNS {
+            let color = [column as f32 / (UV_GRID_COLUMNS - 1) as f32, row as f32 / (UV_GRID_ROWS - 1) as f32, 0.0, 1.0];
+            let (x, cell_width) = split(width, UV_GRID_COLUMNS, column);
+            let (y, cell_height) = split(band_height, UV_GRID_ROWS, row);
+            regions.push((color, vec![rect(x, uv_top + y, cell_width, cell_height)]));
+        }
+    }
+
+    let gamma_top = uv_top + band_height;
+    let lines = (gamma_top..height).step_by(2).map(|y| rect(0, y, width / 2, 1)).collect::<Vec<_>>();
+    regions[1].1.extend(lines);
+    regions.push(([0.5, 0.5, 0.5, 1.0], vec![rect(width / 2, gamma_top, width - width / 2, height - gamma_top)]));
+
+    for (_, rects) in &mut regions { //Zero sized rects are invalid, they show up in tiny windows.
+        rects.retain(|r| r.rect.extent.width > 0 && r.rect.extent.height > 0);
+    }
+    regions.retain(|(_, rects)| !rects.is_empty());
+    regions
+}
+fn split(length: u32, parts: u32, index: u32) -> (u32, u32) { //Offset and size of one of `parts` equal pieces, rounded so they add up to exactly `length`.
+    let start = length * index / parts;
+    let end = length * (index + 1) / parts;
+    (start, end - start)
+}
+fn rect(x: u32, y: u32, width: u32, height: u32) -> vk::ClearRect {
+    vk::ClearRect::builder().rect(vk::Rect2D {offset: vk::Offset2D {x: x as i32, y: y as i32}, extent: vk::Extent2D {width, height}}).base_array_layer(0).layer_count(1).build()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXTENTS: [(u32, u32); 6] = [(1, 1), (2, 1), (3, 7), (33, 65), (1023, 767), (1024, 768)];
+
+    #[test]
+    fn rects_stay_inside_the_extent_and_are_never_empty() {
+        for (width, height) in EXTENTS {
+            for (_, rects) in test_pattern_regions(vk::Extent2D {width, height}) {
+                assert!(!rects.is_empty());
+                for r in rects {
+                    let (offset, extent) = (r.rect.offset, r.rect.extent);
+                    assert!(extent.width > 0 && extent.height > 0, "empty rect {:?} at {}x{}", r.rect, width, height);
+                    assert!(offset.x >= 0 && offset.y >= 0 && offset.x as u32 + extent.width <= width && offset.y as u32 + extent.height <= height,
+                        "rect {:?} outside {}x{}", r.rect, width, height);
+                }
+            }
+        }
+    }
+    #[test]
+    fn the_gamma_patch_is_there() {
+        for (width, height) in EXTENTS {
+            assert!(test_pattern_regions(vk::Extent2D {width, height}).iter().any(|(color, _)| *color == [0.5, 0.5, 0.5, 1.0]), "no patch at {}x{}", width, height);
+        }
+    }
+    #[test]
+    fn split_pieces_add_up_to_the_length() {
+        for (length, parts) in [(0, 3), (1, 16), (100, 16), (1023, 7), (16, 16)] {
+            let pieces = (0..parts).map(|index| split(length, parts, index)).collect::<Vec<_>>();
+            assert_eq!(pieces.iter().map(|(_, size)| size).sum::<u32>(), length);
+            assert!(pieces.windows(2).all(|pair| pair[0].0 + pair[0].1 == pair[1].0), "gaps in {:?}", pieces);
+        }
+    }
+}
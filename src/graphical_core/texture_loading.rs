@@ -0,0 +1,125 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::thread::{self, JoinHandle};
+use anyhow::{anyhow, Context};
+use log::warn;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct TextureHandle(u64);
+
+#[derive(Clone, Debug)]
+pub struct DecodedImage { //Tightly packed RGBA8 pixels, ready to be copied into a staging buffer.
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>
+}
+
+#[derive(Debug)]
+pub struct TextureLoadQueue { //Decodes images on a worker thread, the GPU upload stays on the main thread since queues aren't freely shareable.
+    requests: Option<Sender<(TextureHandle, PathBuf)>>,
+    results: Receiver<(TextureHandle, anyhow::Result<DecodedImage>)>,
+    finished: HashMap<TextureHandle, anyhow::Result<DecodedImage>>,
+    next_handle: u64,
+    worker: Option<JoinHandle<()>>
+}
+impl TextureLoadQueue {
+    pub fn new() -> Self {
+        let (request_sender, request_receiver) = channel::<(TextureHandle, PathBuf)>();
+        let (result_sender, result_receiver) = channel();
+        let worker = thread::spawn(move || {
+            for (handle, path) in request_receiver { //Ends once the queue is dropped and the request sender with it.
+                let decoded = decode_png(&path).with_context(|| format!("decoding `{}`", path.display()));
+                if result_sender.send((handle, decoded)).is_err() {
+                    break;
+                }
+            }
+        });
+        Self {requests: Some(request_sender), results: result_receiver, finished: HashMap::new(), next_handle: 0, worker: Some(worker)}
+    }
+    pub fn request_texture_load(&mut self, path: impl Into<PathBuf>) -> TextureHandle {
+        let handle = TextureHandle(self.next_handle);
+        self.next_handle += 1;
+        let path = path.into();
+        if let Some(requests) = &self.requests {
+            if requests.send((handle, path)).is_err() {
+                self.finished.insert(handle, Err(anyhow!("The texture loading thread has stopped.")));
+            }
+        }
+        handle
+    }
+    pub fn poll(&mut self) { //Collects whatever the worker finished since the last call, meant to be called once per frame.
+        while let Ok((handle, decoded)) = self.results.try_recv() {
+            self.finished.insert(handle, decoded);
+        }
+    }
+    pub fn is_ready(&self, handle: TextureHandle) -> bool {
+        self.finished.contains_key(&handle)
+    }
+    pub fn take(&mut self, handle: TextureHandle) -> Option<anyhow::Result<DecodedImage>> { //None while the image is still being decoded.
+        self.finished.remove(&handle)
+    }
+}
+impl Default for TextureLoadQueue {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl Drop for TextureLoadQueue {
+    fn drop(&mut self) {
+        self.requests = None;
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+    }
+}
+
+fn decode_png(path: &Path) -> anyhow::Result<DecodedImage> {
+    let mut decoder = png::Decoder::new(File::open(path)?);
+    decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+    let mut reader = decoder.read_info()?;
+    let mut buffer = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buffer)?;
+    buffer.truncate(info.buffer_size());
+    let pixels = match info.color_type {
+        png::ColorType::Rgba => buffer,
+        png::ColorType::Rgb => buffer.chunks_exact(3).flat_map(|rgb| [rgb[0], rgb[1], rgb[2], 255]).collect(),
+        png::ColorType::GrayscaleAlpha => buffer.chunks_exact(2).flat_map(|ga| [ga[0], ga[0], ga[0], ga[1]]).collect(),
+        png::ColorType::Grayscale => buffer.iter().flat_map(|&g| [g, g, g, 255]).collect(),
+        png::ColorType::Indexed => return Err(anyhow!("Indexed PNGs should have been expanded by the decoder.")),
+    };
+    Ok(DecodedImage {width: info.width, height: info.height, pixels})
+}
+/// Keeps a requested sampler anisotropy within 1..=`max_sampler_anisotropy`, the range Vulkan accepts for `max_anisotropy`.
+/// Devices differ a lot here (16x on most desktops, less on mobile), so a too-high request is lowered instead of failing validation.
+pub fn clamp_anisotropy(requested: f32, max_sampler_anisotropy: f32) -> f32 {
+    let clamped = requested.clamp(1.0, max_sampler_anisotropy.max(1.0));
+    if clamped != requested {
+        warn!("Requested anisotropy {} is outside the supported 1..={}, using {} instead.", requested, max_sampler_anisotropy, clamped);
+    }
+    clamped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MOCK_MAX_SAMPLER_ANISOTROPY: f32 = 4.0;
+
+    #[test]
+    fn anisotropy_within_the_limit_is_kept() {
+        assert_eq!(clamp_anisotropy(1.0, MOCK_MAX_SAMPLER_ANISOTROPY), 1.0);
+        assert_eq!(clamp_anisotropy(2.5, MOCK_MAX_SAMPLER_ANISOTROPY), 2.5);
+        assert_eq!(clamp_anisotropy(4.0, MOCK_MAX_SAMPLER_ANISOTROPY), 4.0);
+    }
+    #[test]
+    fn anisotropy_above_the_limit_is_clamped_to_it() {
+        assert_eq!(clamp_anisotropy(16.0, MOCK_MAX_SAMPLER_ANISOTROPY), 4.0);
+    }
+    #[test]
+    fn anisotropy_below_one_is_raised_to_one() {
+        assert_eq!(clamp_anisotropy(0.0, MOCK_MAX_SAMPLER_ANISOTROPY), 1.0);
+        assert_eq!(clamp_anisotropy(8.0, 0.0), 1.0); //A limit below the spec minimum still yields a valid value.
+    }
+}
@@ -0,0 +1,77 @@
+use cgmath::{Deg, One, Quaternion, Rotation3, Vector2, Vector3};
+use crate::graphical_core::camera::Mat4;
+
+pub type Vec2 = Vector2<f32>;
+pub type Vec3 = Vector3<f32>;
+pub type Quat = Quaternion<f32>;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Transform { //Shared by the camera and objects, right-handed with -Z as the unrotated forward direction.
+    pub position: Vec3,
+    pub rotation: Quat,
+    pub scale: Vec3
+}
+impl Transform {
+    pub fn new(position: Vec3, rotation: Quat, scale: Vec3) -> Self {
+        Self {position, rotation, scale}
+    }
+    pub fn from_position(position: Vec3) -> Self {
+        Self {position, ..Self::default()}
+    }
+    pub fn matrix(&self) -> Mat4 { //Scales first, then rotates, then translates.
+        Mat4::from_translation(self.position) * Mat4::from(self.rotation) * Mat4::from_nonuniform_scale(self.scale.x, self.scale.y, self.scale.z)
+    }
+    pub fn translate(&mut self, offset: Vec3) {
+        self.position += offset;
+    }
+    pub fn rotate(&mut self, rotation: Quat) { //Applied on top of the current rotation, in world space.
+        self.rotation = rotation * self.rotation;
+    }
+    pub fn rotate_around_axis(&mut self, axis: Vec3, angle: Deg<f32>) {
+        self.rotate(Quat::from_axis_angle(axis, angle));
+    }
+    pub fn forward(&self) -> Vec3 {
+        self.rotation * -Vec3::unit_z()
+    }
+    pub fn right(&self) -> Vec3 {
+        self.rotation * Vec3::unit_x()
+    }
+    pub fn up(&self) -> Vec3 {
+        self.rotation * Vec3::unit_y()
+    }
+}
+impl Default for Transform {
+    fn default() -> Self {
+        Self {position: Vec3::new(0.0, 0.0, 0.0), rotation: Quat::one(), scale: Vec3::new(1.0, 1.0, 1.0)}
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use cgmath::{point3, InnerSpace, Transform as _};
+    use super::*;
+
+    fn assert_close(actual: Vec3, expected: Vec3) {
+        assert!((actual - expected).magnitude() < 1e-5, "{:?} != {:?}", actual, expected);
+    }
+
+    #[test]
+    fn forward_is_negative_z_without_rotation() {
+        assert_close(Transform::default().forward(), Vec3::new(0.0, 0.0, -1.0));
+    }
+    #[test]
+    fn forward_follows_a_quarter_yaw() {
+        let mut transform = Transform::default();
+        transform.rotate_around_axis(Vec3::unit_y(), Deg(90.0));
+        assert_close(transform.forward(), Vec3::new(-1.0, 0.0, 0.0));
+        transform.rotate_around_axis(Vec3::unit_y(), Deg(-180.0));
+        assert_close(transform.forward(), Vec3::new(1.0, 0.0, 0.0));
+    }
+    #[test]
+    fn matrix_scales_then_rotates_then_translates() {
+        let transform = Transform::new(Vec3::new(10.0, 20.0, 30.0), Quat::from_angle_z(Deg(90.0)), Vec3::new(2.0, 3.0, 4.0));
+        let point = transform.matrix().transform_point(point3(1.0, 1.0, 1.0));
+        assert_close(Vec3::new(point.x, point.y, point.z), Vec3::new(7.0, 22.0, 34.0)); //(2, 3, 4) scaled, (-3, 2, 4) rotated, then moved.
+    }
+}
@@ -1,28 +1,176 @@
-use anyhow::anyhow;
+use std::ffi::{c_void, CString};
+use std::mem::size_of;
+use std::sync::Arc;
+use anyhow::{anyhow, Context};
+use log::{info, warn};
 use winit::window::Window;
 use vulkanalia::{
     loader::{LibloadingLoader, LIBRARY},
-    vk::{KhrSurfaceExtension, KhrSwapchainExtension, ExtDebugUtilsExtension},
+    vk::{KhrSurfaceExtension, KhrSwapchainExtension, KhrTimelineSemaphoreExtension, ExtDebugUtilsExtension},
     window as vulkan_window,
     prelude::v1_0::*,
+    Version,
 };
 use crate::graphical_core::{
-    gpu::choose_gpu,
-    swapchain::{create_swapchain, create_swapchain_image_views},
-    render_pass::create_render_pass,
-    pipeline::create_pipeline,
-    extra::{create_command_buffers, create_command_pool, create_frame_buffers, create_instance, create_logical_device, create_sync_objects},
+    buffers::{create_index_buffer, create_vertex_buffer, BufferResource},
+    camera::{compute_model_matrix, compute_projection_matrix, compute_view_matrix, create_uniform_buffers, destroy_uniform_buffers, write_uniform_buffer, ClipPlanes, SceneState, UniformBufferObject},
+    color::PackedColor,
+    descriptors::{create_descriptor_pool, create_descriptor_set_layout, create_descriptor_sets},
+    gpu::{choose_gpu, get_device_info, negotiated_api_version, DeviceInfo},
+    swapchain::{clip_to_extent, create_swapchain, create_swapchain_image_views, get_swapchain_extent, SwapchainSupport, ZeroSizedExtentError},
+    render_pass::{create_render_pass, ClearColor, ColorLoadOp, ACQUIRE_WAIT_STAGE},
+    pipeline::{create_pipeline, DepthBias, MaterialType, SpecializationConstants},
+    memory::{get_memory_budget, tracked_allocated_bytes, MemoryBudgetReport},
+    texture_loading::clamp_anisotropy,
+    transform::{Vec2, Vec3},
+    extra::{DebugCallbackState, create_command_buffers, create_command_pool, record_command_buffer, create_frame_buffers, create_instance, create_logical_device, create_sync_objects, log_startup_summary},
     MAX_FRAMES_IN_FLIGHT
 };
 use crate::VALIDATION_ENABLED;
+use crate::input::InputState;
 
+const FRAME_WAIT_TIMEOUT_NANOSECONDS: u64 = 5_000_000_000;
+const TEAR_LINE_PIXELS_PER_FRAME: u32 = 16;
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Vertex { //Layout has to match the inputs of shader.vert.
+    pub position: Vec2,
+    pub color: Vec3
+}
+impl Vertex {
+    pub const fn new(position: Vec2, color: Vec3) -> Self {
+        Self {position, color}
+    }
+    pub fn binding_description() -> vk::VertexInputBindingDescription {
+        vk::VertexInputBindingDescription::builder().binding(0).stride(size_of::<Vertex>() as u32).input_rate(vk::VertexInputRate::VERTEX).build()
+    }
+    pub fn attribute_descriptions() -> [vk::VertexInputAttributeDescription; 2] {
+        let position = vk::VertexInputAttributeDescription::builder().binding(0).location(0).format(vk::Format::R32G32_SFLOAT).offset(0).build();
+        let color = vk::VertexInputAttributeDescription::builder().binding(0).location(1).format(vk::Format::R32G32B32_SFLOAT).offset(size_of::<Vec2>() as u32).build();
+        [position, color]
+    }
+}
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct PackedVertex { //Vertex with a packed color for memory-compact meshes, 12 instead of 20 bytes. Fits the same shader inputs, the unused alpha is dropped.
+    pub position: Vec2,
+    pub color: PackedColor
+}
+impl PackedVertex {
+    pub fn binding_description() -> vk::VertexInputBindingDescription {
+        vk::VertexInputBindingDescription::builder().binding(0).stride(size_of::<PackedVertex>() as u32).input_rate(vk::VertexInputRate::VERTEX).build()
+    }
+    pub fn attribute_descriptions() -> [vk::VertexInputAttributeDescription; 2] {
+        let position = vk::VertexInputAttributeDescription::builder().binding(0).location(0).format(vk::Format::R32G32_SFLOAT).offset(0).build();
+        let color = vk::VertexInputAttributeDescription::builder().binding(0).location(1).format(PackedColor::FORMAT).offset(size_of::<Vec2>() as u32).build();
+        [position, color]
+    }
+}
+impl From<Vertex> for PackedVertex {
+    fn from(vertex: Vertex) -> Self {
+        Self {position: vertex.position, color: PackedColor::from_rgb(vertex.color)}
+    }
+}
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum VertexLayout { //Which vertex type the vertex buffer holds and the pipeline reads.
+    #[default]
+    Float, //Vertex
+    Packed //PackedVertex
+}
+impl VertexLayout {
+    pub fn binding_description(self) -> vk::VertexInputBindingDescription {
+        match self {
+            VertexLayout::Float => Vertex::binding_description(),
+            VertexLayout::Packed => PackedVertex::binding_description()
+        }
+    }
+    pub fn attribute_descriptions(self) -> [vk::VertexInputAttributeDescription; 2] {
+        match self {
+            VertexLayout::Float => Vertex::attribute_descriptions(),
+            VertexLayout::Packed => PackedVertex::attribute_descriptions()
+        }
+    }
+}
+// A quad made of two triangles that share the diagonal, lying in the XY plane. Both index triples below run counter-clockwise seen from +Z,
+// which is where the camera in camera.rs looks from. That matches front_face(COUNTER_CLOCKWISE) in pipeline.rs, with back face culling the other side is culled.
+pub static VERTICES: [Vertex; 4] = [
+    Vertex::new(Vec2::new(-0.5, -0.5), Vec3::new(1.0, 0.0, 0.0)),
+    Vertex::new(Vec2::new(0.5, -0.5), Vec3::new(0.0, 1.0, 0.0)),
+    Vertex::new(Vec2::new(0.5, 0.5), Vec3::new(0.0, 0.0, 1.0)),
+    Vertex::new(Vec2::new(-0.5, 0.5), Vec3::new(1.0, 1.0, 1.0))
+];
+pub static INDICES: [u16; 6] = [0, 1, 2, 2, 3, 0];
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum SyncStrategy {
+    #[default]
+    FenceBased, //Frames are only throttled by the in-flight fences.
+    QueueWaitIdle //Additionally waits for the presentation queue to go idle every frame, slower but works around drivers that misbehave otherwise.
+}
+#[derive(Clone, Debug)]
+pub struct VulkanApplicationConfig {
+    pub application_name: CString, //Reported to the driver and matched by validation layer config files.
+    pub application_version: Version,
+    pub engine_name: CString,
+    pub engine_version: Version,
+    pub api_version: Version, //Must not exceed the instance version.
+    pub deterministic_gpu_selection: bool, //Sort GPUs by name and pipeline cache UUID before picking one, so the same GPU is chosen every run.
+    pub prefer_software_gpu: bool, //Try software devices like lavapipe before hardware ones, defaults to on when the CI environment variable is set.
+    pub sync_strategy: SyncStrategy,
+    pub validated_frame_count: Option<u64>, //Destroys the debug messenger after this many frames. The layer stays loaded, so this only saves the message reporting, not the validation itself.
+    pub swapchain_image_usage: vk::ImageUsageFlags, //E.g. TRANSFER_SRC for screenshots or STORAGE for compute post-processing, on top of COLOR_ATTACHMENT.
+    pub swapchain_image_array_layers: u32, //More than one layer (stereo/VR) needs multiview in the render pass and pipeline, which isn't supported yet.
+    pub graphics_queue_priority: f32, //In 0..=1, relative to other queues of this device. When graphics and presentation share a family, the higher of the two is used.
+    pub presentation_queue_priority: f32,
+    pub prefer_timeline_semaphore: bool //Throttles frames with one timeline semaphore (VK_KHR_timeline_semaphore) instead of a fence per frame, falls back to fences if unsupported.
+}
+impl Default for VulkanApplicationConfig {
+    fn default() -> Self {
+        Self {application_name: CString::from(c"Vulkan Tutorial"), application_version: Version::V1_0_0, engine_name: CString::from(c"No Engine"), engine_version: Version::V1_0_0,
+            api_version: Version::V1_0_0, deterministic_gpu_selection: false, prefer_software_gpu: std::env::var_os("CI").is_some(), sync_strategy: SyncStrategy::default(), validated_frame_count: None,
+            swapchain_image_usage: vk::ImageUsageFlags::COLOR_ATTACHMENT, swapchain_image_array_layers: 1,
+            graphics_queue_priority: 1.0, presentation_queue_priority: 1.0, prefer_timeline_semaphore: false}
+    }
+}
+#[derive(Clone, Debug, Default)]
+pub struct RenderSettings { //Everything the user can tune at runtime through the setters, kept as a whole when the device is recreated.
+    pub requested_present_mode: Option<vk::PresentModeKHR>,
+    pub color_load_op: ColorLoadOp,
+    pub clear_color: ClearColor,
+    pub depth_bias: Option<DepthBias>, //None disables depth bias.
+    pub blending_enabled: bool, //Standard alpha-over blending, only meant for transparent geometry and overlays.
+    pub material_type: MaterialType,
+    pub min_sample_shading: Option<f32>, //Fraction of samples shaded individually in 0..1, None disables sample shading. Only makes a difference with MSAA.
+    pub specialization_constants: SpecializationConstants,
+    pub test_pattern_enabled: bool, //Draws the calibration pattern from test_pattern.rs instead of the scene.
+    pub border_indicator: Option<[f32; 4]>, //Tints the screen edges as a glanceable debug signal, e.g. red after a validation error. None hides it.
+    pub tear_line_position: Option<u32>, //X coordinate of a vertical bar that sweeps across the screen, tearing shows up as horizontal breaks in it. None hides it.
+    pub debug_winding: bool, //Colors front faces green and back faces red (with culling disabled) to check a mesh's winding.
+    pub clip_planes: ClipPlanes,
+    pub vertex_layout: VertexLayout,
+    pub anisotropy: Option<f32> //Already clamped to the device limit, None disables anisotropic filtering. Only stored until textures get a sampler.
+}
 #[derive(Clone, Debug, Default)]
 pub struct VulkanApplicationData {
+    pub config: VulkanApplicationConfig,
+    pub render_settings: RenderSettings,
     pub surface: vk::SurfaceKHR,
     pub debug_messenger: vk::DebugUtilsMessengerEXT,
+    pub debug_callback_state: Arc<DebugCallbackState>,
+    pub portability_enabled: bool,
+    pub physical_device_properties2_enabled: bool,
+    pub memory_budget_enabled: bool,
+    pub driver_properties_enabled: bool,
+    pub sample_rate_shading_enabled: bool,
+    pub enabled_instance_layers: Vec<String>,
+    pub enabled_instance_extensions: Vec<String>,
+    pub enabled_device_extensions: Vec<String>,
     pub physical_device: vk::PhysicalDevice,
     pub graphics_queue: vk::Queue,
     pub presentation_queue: vk::Queue,
+    pub incremental_present_enabled: bool,
+    pub dirty_regions: Vec<vk::RectLayerKHR>, //Screen regions changed since the last present, handed to the compositor with VK_KHR_incremental_present. Empty means the whole image.
     pub swapchain_format: vk::Format,
     pub swapchain_accepted_images_width_and_height: vk::Extent2D,
     pub swapchain: vk::SwapchainKHR,
@@ -34,12 +182,28 @@ pub struct VulkanApplicationData {
     pub framebuffers: Vec<vk::Framebuffer>,
     pub command_pool: vk::CommandPool,
     pub command_buffers: Vec<vk::CommandBuffer>,
-    pub image_available_semaphore: vk::Semaphore,
-    pub render_finished_semaphore: vk::Semaphore,
+    pub vertex_buffer: Option<Arc<BufferResource>>, //Shared rather than owned so the data stays Clone, freed when the last clone lets go.
+    pub index_buffer: Option<Arc<BufferResource>>,
+    pub index_count: u32, //Indices per draw, set from the uploaded index data.
+    pub uniform_buffer_object: UniformBufferObject, //Copied into the current frame's uniform buffer every frame.
+    pub uniform_buffers: Vec<vk::Buffer>, //Per frame in flight, as are the three below.
+    pub uniform_buffers_memory: Vec<vk::DeviceMemory>,
+    pub uniform_buffers_allocated_size: Vec<vk::DeviceSize>,
+    pub uniform_buffers_ptr: Vec<*mut c_void>, //Persistently mapped.
+    pub descriptor_set_layout: vk::DescriptorSetLayout,
+    pub descriptor_pool: vk::DescriptorPool,
+    pub descriptor_sets: Vec<vk::DescriptorSet>, //Per frame in flight, pointing at that frame's uniform buffer.
+    pub command_buffers_outdated: Vec<bool>, //Per swapchain image, re-recorded the next time that image is acquired.
+    pub command_buffer_frames: Vec<usize>, //Per swapchain image, the frame in flight whose descriptor set its command buffer binds.
     pub image_available_semaphores: Vec<vk::Semaphore>,
     pub render_finished_semaphores: Vec<vk::Semaphore>,
     pub(crate) in_flight_fences: Vec<vk::Fence>,
-    pub(crate) images_in_flight: Vec<vk::Fence>
+    pub(crate) images_in_flight: Vec<vk::Fence>,
+    pub timeline_semaphore_enabled: bool,
+    pub(crate) frame_timeline_semaphore: vk::Semaphore, //Signaled with an increasing value by every submission, only used when timeline semaphores are enabled.
+    pub(crate) frame_timeline_value: u64, //Last value a submission will signal.
+    pub(crate) frame_timeline_values: Vec<u64>, //Per frame in flight, the value its last submission signals. Takes the place of in_flight_fences.
+    pub(crate) image_timeline_values: Vec<u64> //Per swapchain image, takes the place of images_in_flight.
 }
 #[derive(Clone, Debug)]
 pub struct VulkanApplication {
@@ -48,30 +212,34 @@ pub struct VulkanApplication {
     vulkan_application_data: VulkanApplicationData,
     vulkan_logical_device: Device,
     frame: usize,
+    rendered_frames: u64,
+    scene: SceneState, //Advanced by update.
+    previous_scene: SceneState, //The state before the last update, rendering blends it with scene.
+    destroyed: bool, //Set once everything has been torn down, so a second teardown (e.g. closing the window after a failed recreate_device) doesn't destroy it again.
     pub(crate) resized: bool
 }
 impl VulkanApplication {
-    pub unsafe fn create_vulkan_application(user_window: &Window) -> anyhow::Result<Self> {
-        let platform_specific_vulkan_api = LibloadingLoader::new(LIBRARY)?;
+    pub unsafe fn create_vulkan_application(user_window: &Window, config: VulkanApplicationConfig) -> anyhow::Result<Self> {
+        let platform_specific_vulkan_api = LibloadingLoader::new(LIBRARY).context("loading the Vulkan library")?;
         let vulkan_api_entry_point = Entry::new(platform_specific_vulkan_api).map_err(|b| anyhow!("{}", b))?;
-        let mut vulkan_application_data = VulkanApplicationData::default();
-        let vulkan_instance = create_instance(user_window, &vulkan_api_entry_point, &mut vulkan_application_data)?;
-        vulkan_application_data.surface = vulkan_window::create_surface(&vulkan_instance, &user_window, &user_window)?;
-        choose_gpu(&vulkan_instance, &mut vulkan_application_data)?;
-        let vulkan_logical_device = create_logical_device(&vulkan_api_entry_point, &vulkan_instance, &mut vulkan_application_data)?;
-        create_swapchain(user_window, &vulkan_instance, &vulkan_logical_device, &mut vulkan_application_data)?;
-        create_swapchain_image_views(&vulkan_logical_device, &mut vulkan_application_data)?;
-        create_render_pass(&vulkan_instance, &vulkan_logical_device, &mut vulkan_application_data)?;
-        create_pipeline(&vulkan_logical_device, &mut vulkan_application_data)?;
-        create_frame_buffers(&vulkan_logical_device, &mut vulkan_application_data)?;
-        create_command_pool(&vulkan_instance, &vulkan_logical_device, &mut vulkan_application_data)?;
-        create_command_buffers(&vulkan_logical_device, &mut vulkan_application_data)?;
-        create_sync_objects(&vulkan_logical_device, &mut vulkan_application_data)?;
-        Ok(Self{vulkan_entry_point: vulkan_api_entry_point, vulkan_instance, vulkan_application_data, vulkan_logical_device, frame: 0, resized: false})
-    }
-    pub unsafe fn render_frame(&mut self, window: &Window) -> anyhow::Result<()> {
-
-        self.vulkan_logical_device.wait_for_fences(&[self.vulkan_application_data.in_flight_fences[self.frame]], true, u64::MAX, )?;
+        let mut vulkan_application_data = VulkanApplicationData {config, ..Default::default()};
+        let (vulkan_instance, vulkan_logical_device) = create_instance_and_everything_below(user_window, &vulkan_api_entry_point, &mut vulkan_application_data)?;
+        Ok(Self{vulkan_entry_point: vulkan_api_entry_point, vulkan_instance, vulkan_application_data, vulkan_logical_device, frame: 0, rendered_frames: 0,
+            scene: SceneState::default(), previous_scene: SceneState::default(), destroyed: false, resized: false})
+    }
+    pub fn update(&mut self, fixed_dt: f32, input: &mut InputState) { //Advances the simulation by one fixed timestep, called zero or more times before each rendered frame.
+        let (mouse_delta_x, _) = input.take_mouse_delta(); //Drained every step, the first step of a frame gets all motion since the last one.
+        self.previous_scene = self.scene;
+        self.scene = self.scene.step(fixed_dt, mouse_delta_x);
+    }
+    /// `interpolation_factor` is how far the frame is between the previous and the latest update, in 0..1.
+    pub unsafe fn render_frame(&mut self, window: &Window, interpolation_factor: f32) -> anyhow::Result<()> {
+
+        if self.vulkan_application_data.timeline_semaphore_enabled {
+            self.wait_for_timeline_value(self.vulkan_application_data.frame_timeline_values[self.frame], u64::MAX)?;
+        } else {
+            self.vulkan_logical_device.wait_for_fences(&[self.vulkan_application_data.in_flight_fences[self.frame]], true, u64::MAX, )?;
+        }
 
         let result = self.vulkan_logical_device.acquire_next_image_khr(self.vulkan_application_data.swapchain, u64::MAX, self.vulkan_application_data.image_available_semaphores[self.frame], vk::Fence::null());
         let image_index = match result {
@@ -80,94 +248,423 @@ impl VulkanApplication {
             Err(e) => return Err(anyhow!(e))
         };
 
-        if !self.vulkan_application_data.images_in_flight[image_index].is_null() {
-            self.vulkan_logical_device.wait_for_fences(&[self.vulkan_application_data.images_in_flight[image_index]], true, u64::MAX, )?;
+        let timeline_value = self.vulkan_application_data.frame_timeline_value + 1;
+        if self.vulkan_application_data.timeline_semaphore_enabled {
+            self.wait_for_timeline_value(self.vulkan_application_data.image_timeline_values[image_index], u64::MAX)?; //Zero for images that were never submitted, which is always reached.
+            self.vulkan_application_data.image_timeline_values[image_index] = timeline_value;
+        } else {
+            if !self.vulkan_application_data.images_in_flight[image_index].is_null() {
+                self.vulkan_logical_device.wait_for_fences(&[self.vulkan_application_data.images_in_flight[image_index]], true, u64::MAX, )?;
+            }
+
+            self.vulkan_application_data.images_in_flight[image_index] = self.vulkan_application_data.in_flight_fences[self.frame];
         }
 
-        self.vulkan_application_data.images_in_flight[image_index] = self.vulkan_application_data.in_flight_fences[self.frame];
+        if let Some(position) = self.vulkan_application_data.render_settings.tear_line_position {
+            let width = self.vulkan_application_data.swapchain_accepted_images_width_and_height.width.max(1);
+            self.vulkan_application_data.render_settings.tear_line_position = Some((position + TEAR_LINE_PIXELS_PER_FRAME) % width);
+            self.vulkan_application_data.command_buffers_outdated[image_index] = true;
+        }
+        let recorded_for_other_frame = self.vulkan_application_data.command_buffer_frames[image_index] != self.frame; //Images and frames in flight don't pair up one to one.
+        if self.vulkan_application_data.command_buffers_outdated[image_index] || recorded_for_other_frame { //Safe to re-record, the last submission using this image has finished.
+            record_command_buffer(&self.vulkan_instance, &self.vulkan_logical_device, &self.vulkan_application_data, image_index, self.frame)?;
+            self.vulkan_application_data.command_buffers_outdated[image_index] = false;
+            self.vulkan_application_data.command_buffer_frames[image_index] = self.frame;
+        }
+        self.update_uniform_buffer(self.frame, interpolation_factor); //This frame's previous submission was waited for at the top.
 
         let semaphore_to_wait_on_before_execution = &[self.vulkan_application_data.image_available_semaphores[self.frame]];
-        let stage_of_pipeline_to_wait_on_before_execution = &[vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT];
+        let stage_of_pipeline_to_wait_on_before_execution = &[ACQUIRE_WAIT_STAGE]; //Must match the render pass's external dependency, see create_render_pass.
         let command_buffer_to_use_at_execution = &[self.vulkan_application_data.command_buffers[image_index]];
         let semaphores_to_signal_after_command_buffer_finished_executing = &[self.vulkan_application_data.render_finished_semaphores[self.frame]];
         let info_to_submit_to_queue = vk::SubmitInfo::builder().wait_semaphores(semaphore_to_wait_on_before_execution).wait_dst_stage_mask(stage_of_pipeline_to_wait_on_before_execution)
-            .command_buffers(command_buffer_to_use_at_execution).signal_semaphores(semaphores_to_signal_after_command_buffer_finished_executing);
+            .command_buffers(command_buffer_to_use_at_execution);
 
-        self.vulkan_logical_device.reset_fences(&[self.vulkan_application_data.in_flight_fences[self.frame]])?;
+        if self.vulkan_application_data.timeline_semaphore_enabled {
+            let signal_semaphores = &[self.vulkan_application_data.render_finished_semaphores[self.frame], self.vulkan_application_data.frame_timeline_semaphore];
+            let wait_values = &[0]; //Ignored for binary semaphores, but there has to be one value per semaphore.
+            let signal_values = &[0, timeline_value];
+            let mut timeline_info = vk::TimelineSemaphoreSubmitInfo::builder().wait_semaphore_values(wait_values).signal_semaphore_values(signal_values);
+            let info_to_submit_to_queue = info_to_submit_to_queue.signal_semaphores(signal_semaphores).push_next(&mut timeline_info);
+            self.vulkan_logical_device.queue_submit(self.vulkan_application_data.graphics_queue, &[info_to_submit_to_queue], vk::Fence::null())?;
+            self.vulkan_application_data.frame_timeline_value = timeline_value;
+            self.vulkan_application_data.frame_timeline_values[self.frame] = timeline_value;
+        } else {
+            let info_to_submit_to_queue = info_to_submit_to_queue.signal_semaphores(semaphores_to_signal_after_command_buffer_finished_executing);
+            self.vulkan_logical_device.reset_fences(&[self.vulkan_application_data.in_flight_fences[self.frame]])?;
 
-        self.vulkan_logical_device.queue_submit(self.vulkan_application_data.graphics_queue, &[info_to_submit_to_queue], self.vulkan_application_data.in_flight_fences[self.frame])?;
+            self.vulkan_logical_device.queue_submit(self.vulkan_application_data.graphics_queue, &[info_to_submit_to_queue], self.vulkan_application_data.in_flight_fences[self.frame])?;
+        }
 
         let swapchains_to_present_images_to = &[self.vulkan_application_data.swapchain];
         let image_index_in_swapchain = &[image_index as u32];
-        let image_presentation_configuration = vk::PresentInfoKHR::builder()
+        let mut image_presentation_configuration = vk::PresentInfoKHR::builder()
             .wait_semaphores(semaphores_to_signal_after_command_buffer_finished_executing)
             .swapchains(swapchains_to_present_images_to)
             .image_indices(image_index_in_swapchain);
+        let extent = self.vulkan_application_data.swapchain_accepted_images_width_and_height; //Rects outside the image are invalid, clipped again in case the extent changed since they were queued.
+        let dirty_regions = self.vulkan_application_data.dirty_regions.iter().filter_map(|r| clip_to_extent(vk::Rect2D {offset: r.offset, extent: r.extent}, extent))
+            .map(|r| vk::RectLayerKHR {offset: r.offset, extent: r.extent, layer: 0}).collect::<Vec<_>>();
+        let changed_regions = &[vk::PresentRegionKHR::builder().rectangles(&dirty_regions)]; //One per swapchain.
+        let mut present_regions = vk::PresentRegionsKHR::builder().regions(changed_regions);
+        if self.vulkan_application_data.incremental_present_enabled && !dirty_regions.is_empty() {
+            image_presentation_configuration = image_presentation_configuration.push_next(&mut present_regions);
+        }
 
-        self.vulkan_logical_device.queue_wait_idle(self.vulkan_application_data.presentation_queue)?;
+        if self.vulkan_application_data.config.sync_strategy == SyncStrategy::QueueWaitIdle {
+            self.vulkan_logical_device.queue_wait_idle(self.vulkan_application_data.presentation_queue)?;
+        }
         let result = self.vulkan_logical_device.queue_present_khr(self.vulkan_application_data.presentation_queue, &image_presentation_configuration);
 
+        self.vulkan_application_data.dirty_regions.clear();
         let changed = result == Err(vk::ErrorCode::OUT_OF_DATE_KHR);
 
-        //let changed = result == Ok(vk::SuccessCode::SUBOPTIMAL_KHR) || result == Err(vk::ErrorCode::OUT_OF_DATE_KHR);
-        //println!("{:?}", result?);
-
-        if changed {
+        if self.resized || changed { //Resizes and scale factor changes aren't always reported as OUT_OF_DATE by the driver.
+            self.resized = false;
             self.recreate_swapchain(window)?;
+        } else if let Err(e) = result {
+            return Err(anyhow!(e));
         }
-
-        //if self.resized || changed {
-            //self.resized = false;
-            //self.recreate_swapchain(window)?;
-        //} else if let Err(e) = result {
-            //return Err(anyhow!(e));
-        //}
         self.frame = (self.frame + 1) % MAX_FRAMES_IN_FLIGHT;
+        self.rendered_frames += 1;
+        let validation_finished = self.vulkan_application_data.config.validated_frame_count.is_some_and(|count| self.rendered_frames >= count);
+        if VALIDATION_ENABLED && validation_finished && !self.vulkan_application_data.debug_messenger.is_null() {
+            info!("Validated the first {} frames, destroying the debug messenger.", self.rendered_frames);
+            self.vulkan_instance.destroy_debug_utils_messenger_ext(self.vulkan_application_data.debug_messenger, None);
+            self.vulkan_application_data.debug_messenger = vk::DebugUtilsMessengerEXT::null();
+        }
 
         Ok(())
     }
+    unsafe fn update_uniform_buffer(&mut self, frame: usize, interpolation_factor: f32) {
+        let scene = self.previous_scene.interpolate(self.scene, interpolation_factor);
+        let extent = self.vulkan_application_data.swapchain_accepted_images_width_and_height;
+        let aspect_ratio = extent.width as f32 / extent.height.max(1) as f32;
+        self.vulkan_application_data.uniform_buffer_object = UniformBufferObject {
+            model: compute_model_matrix(scene),
+            view: compute_view_matrix(),
+            projection: compute_projection_matrix(aspect_ratio, self.vulkan_application_data.render_settings.clip_planes)
+        };
+        write_uniform_buffer(&self.vulkan_application_data, frame);
+    }
     pub unsafe fn recreate_swapchain(&mut self, user_window: &Window) -> anyhow::Result<()> {
         self.vulkan_logical_device.device_wait_idle()?;
-        println!("Recreating the swapchain!");
+        let capabilities = SwapchainSupport::get(&self.vulkan_instance, &self.vulkan_application_data, self.vulkan_application_data.physical_device)?.capabilities;
+        if let Err(error) = get_swapchain_extent(user_window, capabilities) {
+            if error.is::<ZeroSizedExtentError>() { //Minimized, keep the old swapchain and try again once the window has a size.
+                self.resized = true;
+                return Ok(());
+            }
+            return Err(error);
+        }
+        info!("Recreating the swapchain, the surface reports a current extent of {}x{}.", capabilities.current_extent.width, capabilities.current_extent.height);
+        let previous_format = self.vulkan_application_data.swapchain_format;
         self.destroy_swapchain();
         create_swapchain(user_window, &self.vulkan_instance, &self.vulkan_logical_device, &mut self.vulkan_application_data)?;
         create_swapchain_image_views(&self.vulkan_logical_device, &mut self.vulkan_application_data)?;
-        create_render_pass(&self.vulkan_instance, &self.vulkan_logical_device, &mut self.vulkan_application_data)?;
-        create_pipeline(&self.vulkan_logical_device, &mut self.vulkan_application_data)?;
+        if self.vulkan_application_data.swapchain_format != previous_format { //E.g. after moving the window to a monitor with different capabilities, the render pass was built for the old format.
+            info!("Swapchain format changed from {:?} to {:?}, rebuilding the render pass and pipeline.", previous_format, self.vulkan_application_data.swapchain_format);
+            self.vulkan_logical_device.destroy_pipeline(self.vulkan_application_data.pipeline, None);
+            self.vulkan_logical_device.destroy_pipeline_layout(self.vulkan_application_data.pipeline_layout, None);
+            self.vulkan_logical_device.destroy_render_pass(self.vulkan_application_data.render_pass, None);
+            create_render_pass(&self.vulkan_instance, &self.vulkan_logical_device, &mut self.vulkan_application_data)?;
+            create_pipeline(&self.vulkan_logical_device, &mut self.vulkan_application_data)?;
+        }
         create_frame_buffers(&self.vulkan_logical_device, &mut self.vulkan_application_data)?;
-        create_command_buffers(&self.vulkan_logical_device, &mut self.vulkan_application_data)?;
-        self.vulkan_application_data.images_in_flight.resize(self.vulkan_application_data.swapchain_images.len(), vk::Fence::null());
+        create_command_buffers(&self.vulkan_instance, &self.vulkan_logical_device, &mut self.vulkan_application_data)?;
+        //The new images have never been submitted, and the device_wait_idle above means nothing for the old ones is still running.
+        //Starting every image without an associated fence avoids waiting on a fence that belonged to a different image of the old swapchain.
+        self.vulkan_application_data.images_in_flight = vec![vk::Fence::null(); self.vulkan_application_data.swapchain_images.len()];
+        self.vulkan_application_data.image_timeline_values = vec![0; self.vulkan_application_data.swapchain_images.len()];
+        self.vulkan_application_data.dirty_regions.clear(); //The new images start out with undefined contents, they have to be presented whole.
+        Ok(())
+    }
+    /// Renders a frame and recreates the swapchain right after it, `cycles` times, to flush out bugs that only show up under rapid recreation.
+    /// While it runs only validation messages are shown and they get captured, any warning or error among them fails the run. Replaces previously captured messages.
+    pub unsafe fn stress_recreate_swapchain(&mut self, window: &Window, cycles: u32) -> anyhow::Result<()> {
+        let state = self.vulkan_application_data.debug_callback_state.clone();
+        let previous_message_types = state.shown_message_types();
+        state.set_shown_message_types(vk::DebugUtilsMessageTypeFlagsEXT::VALIDATION);
+        state.clear_captured_messages();
+        state.set_message_capture(true);
+        let result = (0..cycles).try_for_each(|_| {
+            self.render_frame(window, 1.0)?;
+            self.recreate_swapchain(window)
+        });
+        state.set_message_capture(false);
+        state.set_shown_message_types(previous_message_types);
+        result?;
+
+        let messages = state.captured_messages().into_iter().filter(|m| m.severity >= vk::DebugUtilsMessageSeverityFlagsEXT::WARNING).collect::<Vec<_>>(); //INFO and VERBOSE are chatter, not bugs.
+        if let Some(first_message) = messages.first() {
+            return Err(anyhow!("{} validation warnings and errors during {} swapchain recreations, the first one: {}", messages.len(), cycles, first_message.message));
+        }
+        info!("Recreated the swapchain {} times without validation warnings or errors.", cycles);
+        Ok(())
+    }
+    pub unsafe fn recreate_device(&mut self, user_window: &Window) -> anyhow::Result<()> { //Heavy recovery path for DEVICE_LOST (GPU resets, driver updates), rebuilds everything except the entry point.
+        info!("Recreating the instance and device.");
+        self.destroy_vulkan_application();
+        let mut vulkan_application_data = VulkanApplicationData {
+            config: self.vulkan_application_data.config.clone(),
+            render_settings: self.vulkan_application_data.render_settings.clone(),
+            debug_callback_state: self.vulkan_application_data.debug_callback_state.clone(),
+            ..Default::default()
+        };
+        //Built on the side and only swapped in on success. On failure the application stays torn down, only recreate_device may be retried, and whatever the failed attempt created leaks.
+        let (vulkan_instance, vulkan_logical_device) = create_instance_and_everything_below(user_window, &self.vulkan_entry_point, &mut vulkan_application_data)?;
+        self.vulkan_application_data = vulkan_application_data;
+        self.vulkan_instance = vulkan_instance;
+        self.vulkan_logical_device = vulkan_logical_device;
+        self.destroyed = false;
+        self.frame = 0;
+        self.resized = false;
         Ok(())
     }
-    pub unsafe fn destroy_swapchain(&mut self) {
+    /// Escape hatch for calling Vulkan functions this crate doesn't wrap. Destroying or reconfiguring anything the application created through it corrupts its state.
+    pub unsafe fn device_handle(&self) -> &Device {
+        &self.vulkan_logical_device
+    }
+    /// See `device_handle`.
+    pub unsafe fn instance_handle(&self) -> &Instance {
+        &self.vulkan_instance
+    }
+    /// See `device_handle`.
+    pub unsafe fn physical_device_handle(&self) -> vk::PhysicalDevice {
+        self.vulkan_application_data.physical_device
+    }
+    pub unsafe fn device_info(&self) -> DeviceInfo {
+        get_device_info(&self.vulkan_instance, &self.vulkan_application_data)
+    }
+    pub unsafe fn api_version(&self) -> Version { //Gate 1.1+ core features on this rather than on the requested config version.
+        negotiated_api_version(&self.vulkan_instance, &self.vulkan_application_data)
+    }
+    pub unsafe fn memory_budget(&self) -> MemoryBudgetReport { //Without VK_EXT_memory_budget the usage is only our own allocations and the budget is unknown.
+        get_memory_budget(&self.vulkan_instance, &self.vulkan_application_data)
+    }
+    pub fn set_shown_validation_message_types(&self, message_types: vk::DebugUtilsMessageTypeFlagsEXT) { //E.g. hide PERFORMANCE hints while chasing VALIDATION errors, everything is shown by default.
+        self.vulkan_application_data.debug_callback_state.set_shown_message_types(message_types);
+    }
+    pub fn set_validation_message_capture(&self, enabled: bool) { //Collects validation layer messages so a test can assert on them, nothing is captured when validation is disabled.
+        self.vulkan_application_data.debug_callback_state.set_message_capture(enabled);
+    }
+    pub fn validation_messages(&self) -> Vec<String> {
+        self.vulkan_application_data.debug_callback_state.captured_messages().into_iter().map(|m| m.message).collect()
+    }
+    pub fn clear_validation_messages(&self) {
+        self.vulkan_application_data.debug_callback_state.clear_captured_messages();
+    }
+    pub unsafe fn set_present_mode(&mut self, window: &Window, present_mode: Option<vk::PresentModeKHR>) -> anyhow::Result<()> { //None prefers MAILBOX, unsupported modes fall back to FIFO.
+        self.vulkan_application_data.render_settings.requested_present_mode = present_mode;
+        self.recreate_swapchain(window)
+    }
+    pub fn set_tear_line_diagnostics(&mut self, enabled: bool) { //Best combined with IMMEDIATE, re-records a command buffer every frame while enabled.
+        self.vulkan_application_data.render_settings.tear_line_position = enabled.then_some(0);
+        self.mark_command_buffers_outdated();
+    }
+    /// Marks part of the screen as changed this frame, so a mostly static frame (menus, paused game) only has those regions presented.
+    /// The whole frame still gets rendered, this is only a hint to the compositor and is ignored without VK_KHR_incremental_present.
+    /// Frames without any marked region are presented in full.
+    pub fn mark_dirty_region(&mut self, region: vk::Rect2D) { //Clipped to the swapchain image, regions entirely outside it are dropped.
+        if let Some(region) = clip_to_extent(region, self.vulkan_application_data.swapchain_accepted_images_width_and_height) {
+            self.vulkan_application_data.dirty_regions.push(vk::RectLayerKHR {offset: region.offset, extent: region.extent, layer: 0});
+        }
+    }
+    pub fn set_test_pattern(&mut self, enabled: bool) {
+        if self.vulkan_application_data.render_settings.test_pattern_enabled != enabled {
+            self.vulkan_application_data.render_settings.test_pattern_enabled = enabled;
+            self.mark_command_buffers_outdated();
+        }
+    }
+    pub fn test_pattern_enabled(&self) -> bool {
+        self.vulkan_application_data.render_settings.test_pattern_enabled
+    }
+    pub fn vertex_layout(&self) -> VertexLayout {
+        self.vulkan_application_data.render_settings.vertex_layout
+    }
+    pub fn set_border_indicator(&mut self, color: Option<[f32; 4]>) {
+        if self.vulkan_application_data.render_settings.border_indicator != color {
+            self.vulkan_application_data.render_settings.border_indicator = color;
+            self.mark_command_buffers_outdated();
+        }
+    }
+    pub fn set_clip_planes(&mut self, near: f32, far: f32) -> anyhow::Result<()> { //Needs 0 < near < far, takes effect with the next frame's uniform buffer.
+        self.vulkan_application_data.render_settings.clip_planes = ClipPlanes::new(near, far)?;
+        Ok(())
+    }
+    pub fn set_clear_color(&mut self, clear_color: ClearColor) { //Takes effect as each command buffer gets re-recorded.
+        self.vulkan_application_data.render_settings.clear_color = clear_color;
+        self.mark_command_buffers_outdated();
+    }
+    pub fn mark_command_buffers_outdated(&mut self) { //Re-records each swapchain image's command buffer lazily, right before that image is rendered to again.
+        self.vulkan_application_data.command_buffers_outdated.iter_mut().for_each(|outdated| *outdated = true);
+    }
+    pub unsafe fn set_debug_winding(&mut self, enabled: bool) -> anyhow::Result<()> {
+        self.vulkan_application_data.render_settings.debug_winding = enabled;
+        self.rebuild_pipeline()
+    }
+    pub unsafe fn set_blending(&mut self, enabled: bool) -> anyhow::Result<()> {
+        self.vulkan_application_data.render_settings.blending_enabled = enabled;
+        self.rebuild_pipeline()
+    }
+    pub unsafe fn set_vertex_layout(&mut self, vertex_layout: VertexLayout) -> anyhow::Result<()> { //Re-uploads the vertices in the new layout, then rebuilds the pipeline.
+        if self.vulkan_application_data.render_settings.vertex_layout == vertex_layout {
+            return Ok(());
+        }
+        self.wait_all_frames()?;
+        self.vulkan_application_data.render_settings.vertex_layout = vertex_layout;
+        create_scene_vertex_buffer(&self.vulkan_instance, &self.vulkan_logical_device, &mut self.vulkan_application_data)?;
+        self.rebuild_pipeline()
+    }
+    pub unsafe fn set_material_type(&mut self, material_type: MaterialType) -> anyhow::Result<()> {
+        self.vulkan_application_data.render_settings.material_type = material_type;
+        self.rebuild_pipeline()
+    }
+    pub unsafe fn set_specialization_constants(&mut self, specialization_constants: SpecializationConstants) -> anyhow::Result<()> {
+        self.vulkan_application_data.render_settings.specialization_constants = specialization_constants;
+        self.rebuild_pipeline()
+    }
+    pub unsafe fn set_min_sample_shading(&mut self, min_sample_shading: Option<f32>) -> anyhow::Result<()> {
+        if min_sample_shading.is_some() && !self.vulkan_application_data.sample_rate_shading_enabled {
+            return Err(anyhow!("Sample shading requires the sample_rate_shading feature, which the GPU doesn't support."));
+        }
+        self.vulkan_application_data.render_settings.min_sample_shading = min_sample_shading.map(|fraction| fraction.clamp(0.0, 1.0));
+        self.rebuild_pipeline()
+    }
+    pub unsafe fn set_anisotropy(&mut self, anisotropy: Option<f32>) -> anyhow::Result<()> {
+        if anisotropy.is_some_and(f32::is_nan) {
+            return Err(anyhow!("Anisotropy has to be a number."));
+        }
+        let max_sampler_anisotropy = self.vulkan_instance.get_physical_device_properties(self.vulkan_application_data.physical_device).limits.max_sampler_anisotropy;
+        self.vulkan_application_data.render_settings.anisotropy = anisotropy.map(|level| clamp_anisotropy(level, max_sampler_anisotropy));
+        Ok(())
+    }
+    pub fn anisotropy(&self) -> Option<f32> {
+        self.vulkan_application_data.render_settings.anisotropy
+    }
+    pub unsafe fn set_depth_bias(&mut self, depth_bias: Option<DepthBias>) -> anyhow::Result<()> {
+        self.vulkan_application_data.render_settings.depth_bias = depth_bias;
+        self.rebuild_pipeline()
+    }
+    pub unsafe fn set_color_load_op(&mut self, color_load_op: ColorLoadOp) -> anyhow::Result<()> {
+        self.vulkan_application_data.render_settings.color_load_op = color_load_op;
+        self.wait_all_frames()?;
         self.vulkan_application_data.framebuffers.iter().for_each(|framebuffer| self.vulkan_logical_device.destroy_framebuffer(*framebuffer, None));
-        self.vulkan_logical_device.free_command_buffers(self.vulkan_application_data.command_pool, &self.vulkan_application_data.command_buffers);
         self.vulkan_logical_device.destroy_pipeline(self.vulkan_application_data.pipeline, None);
         self.vulkan_logical_device.destroy_pipeline_layout(self.vulkan_application_data.pipeline_layout, None);
         self.vulkan_logical_device.destroy_render_pass(self.vulkan_application_data.render_pass, None);
+        create_render_pass(&self.vulkan_instance, &self.vulkan_logical_device, &mut self.vulkan_application_data)?;
+        create_pipeline(&self.vulkan_logical_device, &mut self.vulkan_application_data)?;
+        create_frame_buffers(&self.vulkan_logical_device, &mut self.vulkan_application_data)?;
+        create_command_buffers(&self.vulkan_instance, &self.vulkan_logical_device, &mut self.vulkan_application_data)?;
+        Ok(())
+    }
+    /// Waits until every frame in flight has finished, before changing resources all of them use.
+    /// Unlike `device_wait_idle` this only waits on our own in-flight fences, so any work submitted outside render_frame isn't covered.
+    pub unsafe fn wait_all_frames(&self) -> anyhow::Result<()> {
+        let result = if self.vulkan_application_data.timeline_semaphore_enabled { //The latest value is only reached once every earlier submission has finished too.
+            self.wait_for_timeline_value(self.vulkan_application_data.frame_timeline_value, FRAME_WAIT_TIMEOUT_NANOSECONDS)?
+        } else {
+            self.vulkan_logical_device.wait_for_fences(&self.vulkan_application_data.in_flight_fences, true, FRAME_WAIT_TIMEOUT_NANOSECONDS)?
+        };
+        if result == vk::SuccessCode::TIMEOUT {
+            return Err(anyhow!("Frames in flight didn't finish within {} seconds, the GPU might be hung.", FRAME_WAIT_TIMEOUT_NANOSECONDS / 1_000_000_000));
+        }
+        Ok(())
+    }
+    unsafe fn wait_for_timeline_value(&self, value: u64, timeout: u64) -> vulkanalia::VkResult<vk::SuccessCode> {
+        let semaphores = &[self.vulkan_application_data.frame_timeline_semaphore];
+        let values = &[value];
+        let info = vk::SemaphoreWaitInfo::builder().semaphores(semaphores).values(values);
+        self.vulkan_logical_device.wait_semaphores_khr(&info, timeout)
+    }
+    unsafe fn rebuild_pipeline(&mut self) -> anyhow::Result<()> {
+        self.wait_all_frames()?;
+        self.vulkan_logical_device.destroy_pipeline(self.vulkan_application_data.pipeline, None);
+        self.vulkan_logical_device.destroy_pipeline_layout(self.vulkan_application_data.pipeline_layout, None);
+        create_pipeline(&self.vulkan_logical_device, &mut self.vulkan_application_data)?;
+        create_command_buffers(&self.vulkan_instance, &self.vulkan_logical_device, &mut self.vulkan_application_data)?;
+        Ok(())
+    }
+    pub unsafe fn destroy_swapchain(&mut self) { //The pipeline is kept, it only uses dynamic viewport and scissor state and a compatible render pass.
+        self.vulkan_application_data.framebuffers.iter().for_each(|framebuffer| self.vulkan_logical_device.destroy_framebuffer(*framebuffer, None));
         self.vulkan_application_data.swapchain_image_views.iter().for_each(|image_view| self.vulkan_logical_device.destroy_image_view(*image_view, None));
         self.vulkan_logical_device.destroy_swapchain_khr(self.vulkan_application_data.swapchain, None);
     }
 
     pub unsafe fn destroy_vulkan_application(&mut self) {
+        if self.destroyed {
+            return;
+        }
+        self.destroyed = true;
+        //Nothing may be destroyed while the GPU could still be rendering or presenting with it. Expected to fail on a lost device, the teardown goes ahead regardless.
+        if let Err(error) = self.vulkan_logical_device.device_wait_idle() {
+            warn!("Waiting for the device to go idle before shutdown failed: {}", error);
+        }
         self.destroy_swapchain();
+        self.vulkan_logical_device.destroy_pipeline(self.vulkan_application_data.pipeline, None);
+        self.vulkan_logical_device.destroy_pipeline_layout(self.vulkan_application_data.pipeline_layout, None);
+        self.vulkan_logical_device.destroy_render_pass(self.vulkan_application_data.render_pass, None);
+        self.vulkan_logical_device.destroy_descriptor_pool(self.vulkan_application_data.descriptor_pool, None);
+        self.vulkan_logical_device.destroy_descriptor_set_layout(self.vulkan_application_data.descriptor_set_layout, None);
         self.vulkan_application_data.in_flight_fences.iter().for_each(|f| self.vulkan_logical_device.destroy_fence(*f, None));
+        self.vulkan_logical_device.destroy_semaphore(self.vulkan_application_data.frame_timeline_semaphore, None); //A no-op for the null handle on the fence path.
         self.vulkan_application_data.render_finished_semaphores.iter().for_each(|s| self.vulkan_logical_device.destroy_semaphore(*s, None));
         self.vulkan_application_data.image_available_semaphores.iter().for_each(|s| self.vulkan_logical_device.destroy_semaphore(*s, None));
-        //self.vulkan_logical_device.destroy_semaphore(self.vulkan_application_data.render_finished_semaphore, None);
-        //self.vulkan_logical_device.destroy_semaphore(self.vulkan_application_data.image_available_semaphore, None);
         self.vulkan_logical_device.destroy_command_pool(self.vulkan_application_data.command_pool, None);
+        self.vulkan_application_data.vertex_buffer = None;
+        self.vulkan_application_data.index_buffer = None;
+        destroy_uniform_buffers(&self.vulkan_logical_device, &mut self.vulkan_application_data);
+        let leaked_bytes = tracked_allocated_bytes();
+        if leaked_bytes != 0 {
+            warn!("{} bytes of device memory are still allocated when destroying the device, something wasn't freed.", leaked_bytes);
+        }
         self.vulkan_logical_device.destroy_device(None);
         self.vulkan_instance.destroy_surface_khr(self.vulkan_application_data.surface, None);
-        if VALIDATION_ENABLED {
+        if VALIDATION_ENABLED && !self.vulkan_application_data.debug_messenger.is_null() {
             self.vulkan_instance.destroy_debug_utils_messenger_ext(self.vulkan_application_data.debug_messenger, None);
          }
         self.vulkan_instance.destroy_instance(None);
     }
-    unsafe fn present_image_to_swapchain(&mut self, present_info: vk::PresentInfoKHRBuilder) {
-        self.vulkan_logical_device.queue_present_khr(self.vulkan_application_data.presentation_queue, &present_info).expect("Presenting the image to the swapchain resulted in an error!");
+}
+
+unsafe fn create_scene_vertex_buffer(instance: &Instance, device: &Device, data: &mut VulkanApplicationData) -> anyhow::Result<()> { //VERTICES in the vertex layout of the render settings.
+    match data.render_settings.vertex_layout {
+        VertexLayout::Float => create_vertex_buffer(instance, device, data, &VERTICES),
+        VertexLayout::Packed => create_vertex_buffer(instance, device, data, &VERTICES.map(PackedVertex::from))
+    }
+}
+unsafe fn create_instance_and_everything_below(user_window: &Window, vulkan_api_entry_point: &Entry, vulkan_application_data: &mut VulkanApplicationData) -> anyhow::Result<(Instance, Device)> { //The surface is created anew as well, it belongs to the instance.
+    let vulkan_instance = create_instance(user_window, vulkan_api_entry_point, vulkan_application_data)?;
+    vulkan_application_data.surface = vulkan_window::create_surface(&vulkan_instance, &user_window, &user_window).context("creating window surface")?;
+    choose_gpu(&vulkan_instance, vulkan_application_data)?;
+    let vulkan_logical_device = create_logical_device(vulkan_api_entry_point, &vulkan_instance, vulkan_application_data)?;
+    log_startup_summary(vulkan_api_entry_point, &vulkan_instance, vulkan_application_data);
+    create_swapchain(user_window, &vulkan_instance, &vulkan_logical_device, vulkan_application_data)?;
+    create_swapchain_image_views(&vulkan_logical_device, vulkan_application_data)?;
+    create_render_pass(&vulkan_instance, &vulkan_logical_device, vulkan_application_data)?;
+    create_descriptor_set_layout(&vulkan_logical_device, vulkan_application_data)?;
+    create_pipeline(&vulkan_logical_device, vulkan_application_data)?;
+    create_frame_buffers(&vulkan_logical_device, vulkan_application_data)?;
+    create_command_pool(&vulkan_instance, &vulkan_logical_device, vulkan_application_data)?;
+    create_scene_vertex_buffer(&vulkan_instance, &vulkan_logical_device, vulkan_application_data)?;
+    create_index_buffer(&vulkan_instance, &vulkan_logical_device, vulkan_application_data, &INDICES)?;
+    create_uniform_buffers(&vulkan_instance, &vulkan_logical_device, vulkan_application_data)?;
+    create_descriptor_pool(&vulkan_logical_device, vulkan_application_data)?;
+    create_descriptor_sets(&vulkan_logical_device, vulkan_application_data)?;
+    create_command_buffers(&vulkan_instance, &vulkan_logical_device, vulkan_application_data)?;
+    create_sync_objects(&vulkan_logical_device, vulkan_application_data)?;
+    Ok((vulkan_instance, vulkan_logical_device))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn packed_layout_describes_packed_vertices() {
+        assert_eq!(VertexLayout::Packed.binding_description().stride, 12);
+        assert_eq!(VertexLayout::Packed.attribute_descriptions()[1].format, vk::Format::R8G8B8A8_UNORM);
+        assert_eq!(VertexLayout::Float.binding_description().stride, size_of::<Vertex>() as u32);
     }
-    //unsafe fn index_of_next_available_presentable_image(&mut self) {
-       // self.vulkan_logical_device.acquire_next_image_khr(self.vulkan_application_data.swapchain, u64::MAX, self.vulkan_application_data.image_available_semaphores[self.frame], vk::Fence::null()).expect("Retrieving the next presentable image index resulted in an error!");
-    //}
 }
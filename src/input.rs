@@ -0,0 +1,117 @@
+use std::collections::HashSet;
+use log::warn;
+use winit::{
+    dpi::PhysicalPosition,
+    event::MouseButton,
+    keyboard::KeyCode,
+    window::{CursorGrabMode, Window}
+};
+
+#[derive(Clone, Debug, Default)]
+pub struct InputState { //Fed by the event loop, queried by the simulation update.
+    keys_down: HashSet<KeyCode>,
+    mouse_buttons_down: HashSet<MouseButton>,
+    mouse_delta: (f64, f64) //Accumulated until taken, so motion between two updates isn't lost.
+}
+impl InputState {
+    pub fn key_pressed(&mut self, key: KeyCode) {
+        self.keys_down.insert(key);
+    }
+    pub fn key_released(&mut self, key: KeyCode) {
+        self.keys_down.remove(&key);
+    }
+    pub fn mouse_button_pressed(&mut self, button: MouseButton) {
+        self.mouse_buttons_down.insert(button);
+    }
+    pub fn mouse_button_released(&mut self, button: MouseButton) {
+        self.mouse_buttons_down.remove(&button);
+    }
+    pub fn mouse_moved(&mut self, delta: (f64, f64)) {
+        self.mouse_delta.0 += delta.0;
+        self.mouse_delta.1 += delta.1;
+    }
+    pub fn release_all(&mut self) { //Keys that are let go while the window is unfocused never send a release event.
+        self.keys_down.clear();
+        self.mouse_buttons_down.clear();
+        self.mouse_delta = (0.0, 0.0);
+    }
+    pub fn is_key_down(&self, key: KeyCode) -> bool {
+        self.keys_down.contains(&key)
+    }
+    pub fn is_mouse_button_down(&self, button: MouseButton) -> bool {
+        self.mouse_buttons_down.contains(&button)
+    }
+    pub fn take_mouse_delta(&mut self) -> (f64, f64) {
+        std::mem::take(&mut self.mouse_delta)
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct CursorCapture {
+    captured: bool,
+    recenter_manually: bool //Set when the platform can only confine the cursor instead of locking it, the cursor is then moved back to the window center after every motion.
+}
+impl CursorCapture {
+    pub fn capture(&mut self, window: &Window) {
+        if window.set_cursor_grab(CursorGrabMode::Locked).is_ok() {
+            self.recenter_manually = false;
+        } else {
+            if let Err(e) = window.set_cursor_grab(CursorGrabMode::Confined) {
+                warn!("Cursor can neither be locked nor confined ({}), falling back to re-centering it.", e);
+            }
+            self.recenter_manually = true;
+        }
+        window.set_cursor_visible(false);
+        self.captured = true;
+    }
+    pub fn release(&mut self, window: &Window) {
+        if let Err(e) = window.set_cursor_grab(CursorGrabMode::None) {
+            warn!("Failed to release the cursor ({}).", e);
+        }
+        window.set_cursor_visible(true);
+        self.captured = false;
+    }
+    pub fn is_captured(&self) -> bool {
+        self.captured
+    }
+    pub fn cursor_moved(&self, window: &Window) {
+        if self.captured && self.recenter_manually {
+            let size = window.inner_size();
+            let _ = window.set_cursor_position(PhysicalPosition::new(size.width / 2, size.height / 2));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keys_are_down_between_press_and_release() {
+        let mut input = InputState::default();
+        input.key_pressed(KeyCode::KeyW);
+        assert!(input.is_key_down(KeyCode::KeyW));
+        assert!(!input.is_key_down(KeyCode::KeyS));
+        input.key_released(KeyCode::KeyW);
+        assert!(!input.is_key_down(KeyCode::KeyW));
+    }
+    #[test]
+    fn mouse_motion_accumulates_until_taken() {
+        let mut input = InputState::default();
+        input.mouse_moved((3.0, -1.0));
+        input.mouse_moved((2.0, 4.0));
+        assert_eq!(input.take_mouse_delta(), (5.0, 3.0));
+        assert_eq!(input.take_mouse_delta(), (0.0, 0.0));
+    }
+    #[test]
+    fn release_all_forgets_keys_buttons_and_motion() {
+        let mut input = InputState::default();
+        input.key_pressed(KeyCode::KeyA);
+        input.mouse_button_pressed(MouseButton::Left);
+        input.mouse_moved((1.0, 1.0));
+        input.release_all();
+        assert!(!input.is_key_down(KeyCode::KeyA));
+        assert!(!input.is_mouse_button_down(MouseButton::Left));
+        assert_eq!(input.take_mouse_delta(), (0.0, 0.0));
+    }
+}
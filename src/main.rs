@@ -5,18 +5,25 @@ clippy::too_many_arguments,
 clippy::unnecessary_wraps
 )]
 mod graphical_core;
+mod input;
+mod terrain;
+mod timing;
 use anyhow::Result;
+use log::{info, warn};
 use winit::{
     dpi::LogicalSize,
-    event::{Event, WindowEvent},
+    event::{DeviceEvent, ElementState, Event, KeyEvent, MouseButton, WindowEvent},
     event_loop::{EventLoop, EventLoopWindowTarget},
+    keyboard::{KeyCode, PhysicalKey},
     window::WindowBuilder
 };
 use vulkanalia::{
     prelude::v1_0::*,
     Version,
 };
-use graphical_core::vulkan_object::VulkanApplication;
+use graphical_core::vulkan_object::{VertexLayout, VulkanApplication, VulkanApplicationConfig};
+use input::{CursorCapture, InputState};
+use timing::{FrameLimiter, FrameTimer, FIXED_TIMESTEP};
 
 const PORTABILITY_MACOS_VERSION: Version = Version::new(1, 3, 216);
 const VALIDATION_ENABLED: bool =
@@ -25,6 +32,7 @@ const VALIDATION_ENABLED: bool =
 const VALIDATION_LAYER: vk::ExtensionName =
     vk::ExtensionName::from_bytes(b"VK_LAYER_KHRONOS_validation");
 const DEVICE_EXTENSIONS: &[vk::ExtensionName] = &[vk::KHR_SWAPCHAIN_EXTENSION.name];
+const DEFAULT_MAX_FPS: u32 = 60; //What F4 caps the frame rate to without --max-fps.
 
 fn main() -> Result<()>
 {
@@ -33,17 +41,62 @@ fn main() -> Result<()>
     let event_handler = EventLoop::new()?;
     let user_window = WindowBuilder::new().with_title("Vulkan Tutorial (Rust)").with_inner_size(LogicalSize::new(1024, 768)).build(&event_handler)?;
 
-    let mut application = unsafe {VulkanApplication::create_vulkan_application(&user_window)}?;
+    let mut application = unsafe {VulkanApplication::create_vulkan_application(&user_window, VulkanApplicationConfig::default())}?;
     let mut destroy_application = false;
     let mut minimized = false;
+    let mut cursor_capture = CursorCapture::default();
+    let mut input = InputState::default();
+    let mut frame_timer = FrameTimer::new();
+    let requested_max_fps = max_fps_from_arguments(std::env::args());
+    let mut frame_limiter = FrameLimiter::new(requested_max_fps);
+    let mut current_monitor = user_window.current_monitor();
 
     event_handler.run(move |event, current_window| {
         match event
         {
             Event::WindowEvent {event: WindowEvent::CloseRequested, .. } => {exit_program(&mut destroy_application, current_window, &mut application);},
             Event::WindowEvent { event: WindowEvent::Resized(size), .. } => {if size.width == 0 || size.height == 0 {minimized = true} else {minimized = false; application.resized = true}},
+            Event::WindowEvent { event: WindowEvent::ScaleFactorChanged {..}, .. } => {application.resized = true}, //The physical pixel size changes with the scale factor, so the swapchain extent has to follow.
+            Event::WindowEvent { event: WindowEvent::Moved(_), .. } => {
+                let monitor = user_window.current_monitor();
+                if monitor != current_monitor { //Recreating the swapchain lets the driver sync to the new monitor's refresh rate.
+                    info!("Window moved to monitor {:?} ({:?} mHz).", monitor.as_ref().and_then(|m| m.name()), monitor.as_ref().and_then(|m| m.refresh_rate_millihertz()));
+                    current_monitor = monitor;
+                    application.resized = true;
+                }
+            },
+            Event::WindowEvent {event: WindowEvent::KeyboardInput {event: KeyEvent {physical_key: PhysicalKey::Code(key), state, ..}, ..}, .. } => {
+                match state {
+                    ElementState::Pressed => input.key_pressed(key),
+                    ElementState::Released => input.key_released(key)
+                }
+                if key == KeyCode::Escape && state == ElementState::Pressed {cursor_capture.release(&user_window)}
+                if key == KeyCode::F2 && state == ElementState::Pressed {application.set_test_pattern(!application.test_pattern_enabled())}
+                if key == KeyCode::F3 && state == ElementState::Pressed { //Switches between float and packed vertex colors, they should look the same.
+                    let vertex_layout = if application.vertex_layout() == VertexLayout::Float {VertexLayout::Packed} else {VertexLayout::Float};
+                    unsafe {application.set_vertex_layout(vertex_layout)}.unwrap();
+                }
+                if key == KeyCode::F4 && state == ElementState::Pressed { //Toggles the cap, using --max-fps when it was given.
+                    frame_limiter.set_max_fps(if frame_limiter.max_fps().is_some() {None} else {Some(requested_max_fps.unwrap_or(DEFAULT_MAX_FPS))});
+                    info!("Frame rate cap: {:?}", frame_limiter.max_fps());
+                }
+            },
+            Event::WindowEvent {event: WindowEvent::MouseInput {state, button, ..}, .. } => {
+                match state {
+                    ElementState::Pressed => input.mouse_button_pressed(button),
+                    ElementState::Released => input.mouse_button_released(button)
+                }
+                if button == MouseButton::Left && state == ElementState::Pressed && !cursor_capture.is_captured() {cursor_capture.capture(&user_window)}
+            },
+            Event::WindowEvent {event: WindowEvent::Focused(false), .. } => {cursor_capture.release(&user_window); input.release_all()},
+            Event::WindowEvent {event: WindowEvent::Focused(true), .. } => {frame_timer.reset_timing()},
+            Event::DeviceEvent {event: DeviceEvent::MouseMotion {delta}, .. } if cursor_capture.is_captured() => {input.mouse_moved(delta); cursor_capture.cursor_moved(&user_window)}, //Only while captured, so moving the free cursor doesn't turn the camera.
             Event::AboutToWait => { user_window.request_redraw()},
-            Event::WindowEvent {event: WindowEvent::RedrawRequested, .. } => {if !destroy_application && !minimized {unsafe {application.render_frame(&user_window)}.unwrap()}},
+            Event::WindowEvent {event: WindowEvent::RedrawRequested, .. } if !destroy_application && !minimized => {
+                for _ in 0..frame_timer.take_fixed_steps() {application.update(FIXED_TIMESTEP, &mut input)}
+                unsafe {application.render_frame(&user_window, frame_timer.interpolation_factor())}.unwrap();
+                frame_limiter.wait_for_next_frame()
+            },
             _ => ()
         }
     }).expect("Main function crashed!");
@@ -55,4 +108,30 @@ fn exit_program(destroy_application: &mut bool, current_window: &EventLoopWindow
     current_window.exit();
     unsafe {application.destroy_vulkan_application()}
 }
-fn initialize_error_handler() { pretty_env_logger::init(); }
\ No newline at end of file
+fn max_fps_from_arguments(mut arguments: impl Iterator<Item = String>) -> Option<u32> { //`--max-fps 60` caps the frame rate, without it rendering is unbounded.
+    arguments.find(|argument| argument == "--max-fps")?;
+    let value = arguments.next();
+    let max_fps = value.as_deref().and_then(|fps| fps.parse().ok()).filter(|fps| *fps > 0);
+    if max_fps.is_none() {
+        warn!("--max-fps needs a positive whole number, got {:?}. Running uncapped.", value);
+    }
+    max_fps
+}
+fn initialize_error_handler() { pretty_env_logger::init(); }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn arguments(arguments: &[&str]) -> impl Iterator<Item = String> {
+        arguments.iter().map(|argument| argument.to_string()).collect::<Vec<_>>().into_iter()
+    }
+
+    #[test]
+    fn max_fps_is_read_from_the_arguments() {
+        assert_eq!(max_fps_from_arguments(arguments(&["vulkan-tutorial", "--max-fps", "60"])), Some(60));
+        assert_eq!(max_fps_from_arguments(arguments(&["vulkan-tutorial"])), None);
+        assert_eq!(max_fps_from_arguments(arguments(&["vulkan-tutorial", "--max-fps", "0"])), None);
+        assert_eq!(max_fps_from_arguments(arguments(&["vulkan-tutorial", "--max-fps"])), None);
+    }
+}
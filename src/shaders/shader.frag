@@ -1,9 +1,17 @@
 #version 450
 
+layout(push_constant) uniform PushConstants {
+    uint debugWinding;
+} pushConstants;
+
 layout(location = 0) in vec3 fragColor;
 
 layout(location = 0) out vec4 outColor;
 
 void main() {
-    outColor = vec4(fragColor, 1.0);
-}
\ No newline at end of file
+    if (pushConstants.debugWinding != 0) {
+        outColor = gl_FrontFacing ? vec4(0.0, 1.0, 0.0, 1.0) : vec4(1.0, 0.0, 0.0, 1.0);
+    } else {
+        outColor = vec4(fragColor, 1.0);
+    }
+}
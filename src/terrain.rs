@@ -0,0 +1,90 @@
+use cgmath::Vector3;
+
+pub type IVec3 = Vector3<i32>;
+pub type BlockId = u8;
+
+pub const CHUNK_SIZE: usize = 16; //Blocks along each axis.
+pub const AIR: BlockId = 0;
+pub const STONE: BlockId = 1;
+pub const DIRT: BlockId = 2;
+pub const GRASS: BlockId = 3;
+
+const NOISE_CELL_SIZE: i32 = 8; //Blocks between two noise lattice points, larger gives smoother hills.
+const BASE_HEIGHT: f32 = 8.0;
+const HEIGHT_AMPLITUDE: f32 = 12.0;
+const DIRT_DEPTH: i32 = 3;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Chunk { //A cube of CHUNK_SIZE³ blocks, indexed x first, then y, then z.
+    pub coord: IVec3, //In chunks, the chunk's first block is at coord * CHUNK_SIZE.
+    pub blocks: Box<[BlockId; CHUNK_SIZE * CHUNK_SIZE * CHUNK_SIZE]>
+}
+impl Chunk {
+    pub fn empty(coord: IVec3) -> Self {
+        Self {coord, blocks: Box::new([AIR; CHUNK_SIZE * CHUNK_SIZE * CHUNK_SIZE])}
+    }
+    pub fn block(&self, x: usize, y: usize, z: usize) -> BlockId {
+        self.blocks[Self::index(x, y, z)]
+    }
+    pub fn set_block(&mut self, x: usize, y: usize, z: usize, block: BlockId) {
+        self.blocks[Self::index(x, y, z)] = block;
+    }
+    fn index(x: usize, y: usize, z: usize) -> usize {
+        x + CHUNK_SIZE * (y + CHUNK_SIZE * z)
+    }
+}
+
+// Heightmap terrain from 2D value noise, Y is up. The same seed and coord always give the same chunk, and neighbouring chunks line up
+// because the noise is sampled in world coordinates.
+pub fn generate_chunk(chunk_coord: IVec3, seed: u64) -> Chunk {
+    let mut chunk = Chunk::empty(chunk_coord);
+    let origin = chunk_coord * CHUNK_SIZE as i32;
+    for z in 0..CHUNK_SIZE {
+        for x in 0..CHUNK_SIZE {
+            let surface = (BASE_HEIGHT + HEIGHT_AMPLITUDE * value_noise(origin.x + x as i32, origin.z + z as i32, seed)) as i32;
+            for y in 0..CHUNK_SIZE {
+                let world_y = origin.y + y as i32;
+                let block = if world_y > surface {AIR} else if world_y == surface {GRASS} else if world_y > surface - DIRT_DEPTH {DIRT} else {STONE};
+                chunk.set_block(x, y, z, block);
+            }
+        }
+    }
+    chunk
+}
+fn value_noise(x: i32, z: i32, seed: u64) -> f32 { //In 0..1, random values on a lattice blended with smoothstep.
+    let (cell_x, cell_z) = (x.div_euclid(NOISE_CELL_SIZE), z.div_euclid(NOISE_CELL_SIZE));
+    let smooth = |t: f32| t * t * (3.0 - 2.0 * t);
+    let tx = smooth(x.rem_euclid(NOISE_CELL_SIZE) as f32 / NOISE_CELL_SIZE as f32);
+    let tz = smooth(z.rem_euclid(NOISE_CELL_SIZE) as f32 / NOISE_CELL_SIZE as f32);
+    let lerp = |a: f32, b: f32, t: f32| a + (b - a) * t;
+    let corner = |dx: i32, dz: i32| lattice_value(cell_x + dx, cell_z + dz, seed);
+    lerp(lerp(corner(0, 0), corner(1, 0), tx), lerp(corner(0, 1), corner(1, 1), tx), tz)
+}
+fn lattice_value(x: i32, z: i32, seed: u64) -> f32 { //SplitMix64 finalizer over the packed coordinates, in 0..1.
+    let mut hash = seed ^ ((x as u32 as u64) << 32 | z as u32 as u64);
+    hash = (hash ^ (hash >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    hash = (hash ^ (hash >> 27)).wrapping_mul(0x94d049bb133111eb);
+    hash ^= hash >> 31;
+    (hash >> 40) as f32 / (1u64 << 24) as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use cgmath::vec3;
+    use super::*;
+
+    #[test]
+    fn same_seed_and_coord_give_the_same_chunk() {
+        assert_eq!(generate_chunk(vec3(3, 0, -7), 42).blocks, generate_chunk(vec3(3, 0, -7), 42).blocks);
+    }
+    #[test]
+    fn different_seeds_give_different_terrain() {
+        assert_ne!(generate_chunk(vec3(0, 0, 0), 1).blocks, generate_chunk(vec3(0, 0, 0), 2).blocks);
+    }
+    #[test]
+    fn value_noise_stays_in_range() {
+        for (x, z) in [(0, 0), (-1, 5), (1000, -1000), (7, 9)] {
+            assert!((0.0..1.0).contains(&value_noise(x, z, 7)));
+        }
+    }
+}
@@ -0,0 +1,79 @@
+use std::thread;
+use std::time::{Duration, Instant};
+
+pub const FIXED_TIMESTEP: f32 = 1.0 / 60.0; //Seconds of simulated time per update, independent of the frame rate.
+const MAX_FRAME_DELTA: f32 = 0.25; //Longer frames (stalls, breakpoints, dragging the window) are treated as this long so the simulation doesn't jump ahead.
+
+#[derive(Copy, Clone, Debug)]
+pub struct FrameTimer {
+    last_frame_time: Instant,
+    accumulator: f32 //Elapsed time that hasn't been simulated yet.
+}
+impl FrameTimer {
+    pub fn new() -> Self {
+        Self {last_frame_time: Instant::now(), accumulator: 0.0}
+    }
+    pub fn take_fixed_steps(&mut self) -> u32 { //How many fixed updates have to run before the next frame is rendered, the remainder carries over.
+        let now = Instant::now();
+        self.accumulator += now.duration_since(self.last_frame_time).as_secs_f32().min(MAX_FRAME_DELTA);
+        self.last_frame_time = now;
+
+        let steps = (self.accumulator / FIXED_TIMESTEP) as u32;
+        self.accumulator -= steps as f32 * FIXED_TIMESTEP;
+        steps
+    }
+    pub fn reset_timing(&mut self) { //Forgets the time spent while paused or unfocused, so the next frame doesn't simulate it all at once.
+        self.last_frame_time = Instant::now();
+        self.accumulator = 0.0;
+    }
+    pub fn interpolation_factor(&self) -> f32 { //How far rendering is between the last and the next fixed update, in 0..1.
+        self.accumulator / FIXED_TIMESTEP
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct FrameLimiter {
+    max_fps: Option<u32>, //None renders as fast as the present mode allows.
+    last_frame_end: Instant
+}
+impl FrameLimiter {
+    pub fn new(max_fps: Option<u32>) -> Self {
+        Self {max_fps, last_frame_end: Instant::now()}
+    }
+    pub fn set_max_fps(&mut self, max_fps: Option<u32>) {
+        self.max_fps = max_fps;
+    }
+    pub fn max_fps(&self) -> Option<u32> {
+        self.max_fps
+    }
+    pub fn wait_for_next_frame(&mut self) { //Sleeps away whatever is left of the frame budget, called right after rendering.
+        if let Some(remaining) = remaining_frame_time(self.max_fps, self.last_frame_end.elapsed()) {
+            thread::sleep(remaining);
+        }
+        self.last_frame_end = Instant::now();
+    }
+}
+fn remaining_frame_time(max_fps: Option<u32>, elapsed: Duration) -> Option<Duration> { //None when uncapped or the frame already used up its budget.
+    let frame_budget = Duration::from_secs_f64(1.0 / max_fps.filter(|fps| *fps > 0)? as f64);
+    frame_budget.checked_sub(elapsed).filter(|remaining| !remaining.is_zero())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_capped_frame_waits_out_the_rest_of_its_budget() {
+        assert_eq!(remaining_frame_time(Some(50), Duration::from_millis(5)), Some(Duration::from_millis(15)));
+    }
+    #[test]
+    fn a_frame_over_budget_doesnt_wait() {
+        assert_eq!(remaining_frame_time(Some(50), Duration::from_millis(20)), None);
+        assert_eq!(remaining_frame_time(Some(50), Duration::from_millis(35)), None);
+    }
+    #[test]
+    fn uncapped_frames_never_wait() {
+        assert_eq!(remaining_frame_time(None, Duration::ZERO), None);
+        assert_eq!(remaining_frame_time(Some(0), Duration::ZERO), None);
+    }
+}